@@ -1,17 +1,114 @@
-#[cfg(feature = "serde_codegen")]
-fn main() {
-    extern crate serde_codegen;
+extern crate serde_json;
 
-    use std::env;
-    use std::path::Path;
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
 
-    let out_dir = env::var_os("OUT_DIR").unwrap();
+use serde_json::Value;
+
+#[cfg(feature = "serde_codegen")]
+fn codegen(out_dir: &Path) {
+    extern crate serde_codegen;
 
     let src = Path::new("src/serde_types.in.rs");
-    let dst = Path::new(&out_dir).join("serde_types.rs");
+    let dst = out_dir.join("serde_types.rs");
 
     serde_codegen::expand(&src, &dst).unwrap();
+    println!("cargo:rerun-if-changed=src/serde_types.in.rs");
 }
 
 #[cfg(not(feature = "serde_codegen"))]
-fn main() {}
\ No newline at end of file
+fn codegen(_: &Path) {}
+
+/// The JSON type required of a field of a snapshot.
+#[derive(Clone, Copy)]
+enum Field {
+    Integer,
+    String,
+    /// An object whose values are all numbers, such as `rates`.
+    Numbers,
+}
+
+impl Field {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Field::Integer => value.as_i64().is_some(),
+            Field::String => value.is_string(),
+            Field::Numbers => {
+                value.as_object().map_or(false, |object| object.values().all(Value::is_number))
+            }
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Field::Integer => "an integer",
+            Field::String => "a string",
+            Field::Numbers => "an object of numbers",
+        }
+    }
+}
+
+/// Copy the JSON file named by the `var` environment variable to `name` in `out_dir`, for the
+/// `snapshot` module to embed. An empty file is written when `var` is not set.
+///
+/// The path must be absolute, since build scripts run in the directory of the crate rather than
+/// where `cargo build` was invoked. The build fails unless the file holds a JSON object with
+/// every field of `fields`, of the given type.
+fn snapshot(out_dir: &Path, var: &str, name: &str, fields: &[(&str, Field)]) {
+    println!("cargo:rerun-if-env-changed={}", var);
+
+    let mut contents = String::new();
+    if let Some(path) = env::var_os(var) {
+        let path = Path::new(&path);
+        if !path.is_absolute() {
+            panic!("{}={}: the path must be absolute, since build scripts run in the directory \
+                    of the crate",
+                   var,
+                   path.display());
+        }
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .unwrap_or_else(|err| panic!("{}={}: {}", var, path.display(), err));
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        let value: Value = serde_json::from_str(&contents)
+            .unwrap_or_else(|err| panic!("{}={}: invalid JSON: {}", var, path.display(), err));
+        let object = value.as_object()
+            .unwrap_or_else(|| panic!("{}={}: not a JSON object", var, path.display()));
+        for &(field, kind) in fields {
+            match object.get(field) {
+                Some(value) if kind.matches(value) => {}
+                Some(_) => {
+                    panic!("{}={}: field `{}` must be {}",
+                           var,
+                           path.display(),
+                           field,
+                           kind.describe())
+                }
+                None => panic!("{}={}: missing field `{}`", var, path.display(), field),
+            }
+        }
+    }
+
+    File::create(out_dir.join(name))
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .unwrap();
+}
+
+fn main() {
+    let out_dir = env::var_os("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir);
+
+    codegen(out_dir);
+    snapshot(out_dir,
+             "OXR_SNAPSHOT_RATES",
+             "snapshot_rates.json",
+             &[("disclaimer", Field::String),
+               ("license", Field::String),
+               ("timestamp", Field::Integer),
+               ("base", Field::String),
+               ("rates", Field::Numbers)]);
+    snapshot(out_dir, "OXR_SNAPSHOT_CURRENCIES", "snapshot_currencies.json", &[]);
+}
@@ -0,0 +1,285 @@
+//! Analytics module
+//!
+//! Statistics over the rates of one currency across a series of snapshots, typically daily
+//! results of `Client::historical`:
+//!
+//! ```ignore
+//! let series = Series::from_rates(&rates, "MYR");
+//! let volatility = series.annualized_volatility(252.0);
+//! let trend = series.rolling_mean(20);
+//! let risk = series.log_returns().rolling_std(20);
+//! ```
+use chrono::NaiveDate;
+
+use ExchangeRate;
+
+/// Dated values of one currency, sorted by date.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series {
+    pub currency: String,
+    pub points: Vec<(NaiveDate, f64)>,
+}
+
+impl Series {
+    /// The rates of `currency` in `rates`, skipping snapshots without it.
+    ///
+    /// If several snapshots fall on the same date, the latest one is kept.
+    pub fn from_rates(rates: &[ExchangeRate], currency: &str) -> Series {
+        let mut snapshots: Vec<&ExchangeRate> = rates.iter()
+            .filter(|rate| rate.rates.contains_key(currency))
+            .collect();
+        snapshots.sort_by_key(|rate| rate.timestamp);
+
+        let mut points: Vec<(NaiveDate, f64)> = Vec::with_capacity(snapshots.len());
+        for rate in snapshots {
+            let date = rate.date();
+            if points.last().map(|&(last, _)| last) == Some(date) {
+                points.pop();
+            }
+            points.push((date, rate.rates[currency]));
+        }
+
+        Series {
+            currency: currency.to_owned(),
+            points: points,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The values without their dates.
+    pub fn values(&self) -> Vec<f64> {
+        self.points.iter().map(|&(_, value)| value).collect()
+    }
+
+    /// `value / previous - 1` for every value but the first.
+    pub fn simple_returns(&self) -> Series {
+        self.returns(|previous, value| value / previous - 1.0)
+    }
+
+    /// `ln(value / previous)` for every value but the first.
+    pub fn log_returns(&self) -> Series {
+        self.returns(|previous, value| (value / previous).ln())
+    }
+
+    /// The mean of every `window` consecutive values, dated at the last one.
+    pub fn rolling_mean(&self, window: usize) -> Series {
+        self.rolling(window, mean)
+    }
+
+    /// The sample standard deviation of every `window` consecutive values, dated at the last
+    /// one.
+    pub fn rolling_std(&self, window: usize) -> Series {
+        self.rolling(window, std_dev)
+    }
+
+    /// The standard deviation of the log returns, scaled to a year of `periods_per_year`
+    /// observations, e.g. 252 for business days or 365 for calendar days.
+    ///
+    /// Returns `None` if there are fewer than three values.
+    pub fn annualized_volatility(&self, periods_per_year: f64) -> Option<f64> {
+        let returns = self.log_returns().values();
+        if returns.len() < 2 {
+            return None;
+        }
+        Some(std_dev(&returns) * periods_per_year.sqrt())
+    }
+
+    /// The lowest value and its date, the earliest one on ties.
+    pub fn min(&self) -> Option<(NaiveDate, f64)> {
+        self.points.iter().fold(None, |min, &point| match min {
+            Some((_, value)) if value <= point.1 => min,
+            _ => Some(point),
+        })
+    }
+
+    /// The highest value and its date, the earliest one on ties.
+    pub fn max(&self) -> Option<(NaiveDate, f64)> {
+        self.points.iter().fold(None, |max, &point| match max {
+            Some((_, value)) if value >= point.1 => max,
+            _ => Some(point),
+        })
+    }
+
+    fn returns<F>(&self, f: F) -> Series
+        where F: Fn(f64, f64) -> f64
+    {
+        Series {
+            currency: self.currency.clone(),
+            points: self.points
+                .windows(2)
+                .map(|pair| (pair[1].0, f(pair[0].1, pair[1].1)))
+                .collect(),
+        }
+    }
+
+    fn rolling<F>(&self, window: usize, f: F) -> Series
+        where F: Fn(&[f64]) -> f64
+    {
+        let values = self.values();
+        let points = if window == 0 || window > values.len() {
+            Vec::new()
+        } else {
+            values.windows(window)
+                .zip(&self.points[window - 1..])
+                .map(|(values, &(date, _))| (date, f(values)))
+                .collect()
+        };
+        Series {
+            currency: self.currency.clone(),
+            points: points,
+        }
+    }
+}
+
+/// The Pearson correlation of the log returns of `a` and `b`, over the dates both have.
+///
+/// Returns `None` if there are fewer than two common returns or either is constant.
+pub fn correlation(a: &Series, b: &Series) -> Option<f64> {
+    let b_returns = b.log_returns();
+    let pairs: Vec<(f64, f64)> = a.log_returns()
+        .points
+        .iter()
+        .filter_map(|&(date, x)| {
+            b_returns.points
+                .binary_search_by(|&(other, _)| other.cmp(&date))
+                .ok()
+                .map(|index| (x, b_returns.points[index].1))
+        })
+        .collect();
+    if pairs.len() < 2 {
+        return None;
+    }
+
+    let xs: Vec<f64> = pairs.iter().map(|&(x, _)| x).collect();
+    let ys: Vec<f64> = pairs.iter().map(|&(_, y)| y).collect();
+    let (mean_x, mean_y) = (mean(&xs), mean(&ys));
+    let covariance = pairs.iter().map(|&(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+    let variance_x = xs.iter().map(|x| (x - mean_x).powi(2)).sum::<f64>();
+    let variance_y = ys.iter().map(|y| (y - mean_y).powi(2)).sum::<f64>();
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Sample standard deviation, zero for a single value.
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    let sum_squares = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>();
+    (sum_squares / (values.len() - 1) as f64).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use ExchangeRate;
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn rates(values: &[(&str, &[f64])]) -> Vec<ExchangeRate> {
+        let days = values[0].1.len();
+        (0..days)
+            .map(|day| {
+                let rates: Vec<(&str, f64)> = values.iter()
+                    .map(|&(currency, values)| (currency, values[day]))
+                    .collect();
+                ExchangeRate::fixture(1459814400 + day as i64 * 86400, "USD", &rates)
+            })
+            .collect()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2016, 4, day)
+    }
+
+    #[test]
+    fn from_rates_sorts_and_dates() {
+        let mut rates = rates(&[("MYR", &[4.0, 4.2, 4.1])]);
+        rates.reverse();
+        let series = Series::from_rates(&rates, "MYR");
+        assert_eq!(series.points, vec![(date(5), 4.0), (date(6), 4.2), (date(7), 4.1)]);
+        assert!(Series::from_rates(&rates, "EUR").is_empty());
+    }
+
+    #[test]
+    fn returns() {
+        let series = Series::from_rates(&rates(&[("MYR", &[4.0, 5.0, 4.0])]), "MYR");
+
+        let simple = series.simple_returns();
+        assert_eq!(simple.len(), 2);
+        assert_eq!(simple.points[0], (date(6), 0.25));
+        assert_eq!(simple.points[1].0, date(7));
+        assert!(close(simple.points[1].1, -0.2));
+
+        let log = series.log_returns();
+        assert_eq!(log.len(), 2);
+        assert!(close(log.points[0].1, (1.25f64).ln()));
+        assert!(close(log.points[1].1, (0.8f64).ln()));
+    }
+
+    #[test]
+    fn rolling() {
+        let series = Series::from_rates(&rates(&[("MYR", &[1.0, 2.0, 3.0, 4.0])]), "MYR");
+
+        assert_eq!(series.rolling_mean(2).points,
+                   vec![(date(6), 1.5), (date(7), 2.5), (date(8), 3.5)]);
+        let std = series.rolling_std(3);
+        assert_eq!(std.len(), 2);
+        assert_eq!(std.points[0].0, date(7));
+        assert!(close(std.points[0].1, 1.0));
+        assert!(series.rolling_mean(5).is_empty());
+        assert!(series.rolling_mean(0).is_empty());
+    }
+
+    #[test]
+    fn volatility() {
+        let series = Series::from_rates(&rates(&[("MYR", &[4.0, 4.0, 4.0])]), "MYR");
+        assert_eq!(series.annualized_volatility(252.0), Some(0.0));
+
+        let series = Series::from_rates(&rates(&[("MYR", &[1.0, 2.0, 1.0])]), "MYR");
+        let expected = (2.0 * (2.0f64).ln().powi(2)).sqrt() * (252.0f64).sqrt();
+        assert!(close(series.annualized_volatility(252.0).unwrap(), expected));
+
+        let series = Series::from_rates(&rates(&[("MYR", &[1.0, 2.0])]), "MYR");
+        assert_eq!(series.annualized_volatility(252.0), None);
+    }
+
+    #[test]
+    fn min_max() {
+        let series = Series::from_rates(&rates(&[("MYR", &[4.1, 3.9, 4.3, 3.9])]), "MYR");
+        assert_eq!(series.min(), Some((date(6), 3.9)));
+        assert_eq!(series.max(), Some((date(7), 4.3)));
+    }
+
+    #[test]
+    fn correlations() {
+        let rates = rates(&[("EUR", &[0.8, 0.88, 0.84, 0.9]),
+                            ("DKK", &[6.0, 6.6, 6.3, 6.75]),
+                            ("JPY", &[100.0, 90.0, 95.0, 89.0])]);
+        let eur = Series::from_rates(&rates, "EUR");
+        let dkk = Series::from_rates(&rates, "DKK");
+        let jpy = Series::from_rates(&rates, "JPY");
+
+        assert!(close(correlation(&eur, &dkk).unwrap(), 1.0));
+        assert!(correlation(&eur, &jpy).unwrap() < -0.9);
+        assert_eq!(correlation(&eur, &Series::from_rates(&rates[..2], "DKK")), None);
+    }
+}
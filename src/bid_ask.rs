@@ -0,0 +1,108 @@
+//! Bid/ask module
+use std::collections::BTreeMap;
+
+use {BidAskRate, ExchangeRate, Quote};
+
+impl Quote {
+    /// The difference between the ask and the bid.
+    pub fn spread(&self) -> f64 {
+        self.ask - self.bid
+    }
+
+    /// The spread relative to the mid, in basis points, or `None` if the mid is zero.
+    pub fn spread_bps(&self) -> Option<f64> {
+        if self.mid == 0.0 {
+            None
+        } else {
+            Some(self.spread() / self.mid * 10_000.0)
+        }
+    }
+}
+
+impl BidAskRate {
+    /// The mid rates, which are what the API returns without `show_bid_ask`.
+    pub fn mid_rates(&self) -> ExchangeRate {
+        self.rates_by(|quote| quote.mid)
+    }
+
+    /// The bid rates.
+    pub fn bid_rates(&self) -> ExchangeRate {
+        self.rates_by(|quote| quote.bid)
+    }
+
+    /// The ask rates.
+    pub fn ask_rates(&self) -> ExchangeRate {
+        self.rates_by(|quote| quote.ask)
+    }
+
+    /// The spread of every currency in basis points, leaving out those with a zero mid.
+    pub fn spreads_bps(&self) -> BTreeMap<String, f64> {
+        self.rates
+            .iter()
+            .filter_map(|(currency, quote)| quote.spread_bps().map(|bps| (currency.clone(), bps)))
+            .collect()
+    }
+
+    fn rates_by<F>(&self, f: F) -> ExchangeRate
+        where F: Fn(&Quote) -> f64
+    {
+        ExchangeRate {
+            disclaimer: self.disclaimer.clone(),
+            license: self.license.clone(),
+            timestamp: self.timestamp,
+            base: self.base.clone(),
+            rates: self.rates
+                .iter()
+                .map(|(currency, quote)| (currency.clone(), f(quote)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use {BidAskRate, Quote};
+
+    fn quote(bid: f64, ask: f64, mid: f64) -> Quote {
+        Quote {
+            bid: bid,
+            ask: ask,
+            mid: mid,
+        }
+    }
+
+    #[test]
+    fn spread_bps() {
+        let quote = quote(0.9995, 1.0005, 1.0);
+        assert!((quote.spread() - 0.001).abs() < 1e-12);
+        assert!((quote.spread_bps().unwrap() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn spread_bps_zero_mid() {
+        assert_eq!(quote(0.0, 0.0, 0.0).spread_bps(), None);
+    }
+
+    #[test]
+    fn split_rates() {
+        let mut rates = BTreeMap::new();
+        rates.insert("MYR".to_owned(), quote(3.9, 4.1, 4.0));
+        rates.insert("VEF".to_owned(), quote(0.0, 0.0, 0.0));
+        let rate = BidAskRate {
+            disclaimer: String::new(),
+            license: String::new(),
+            timestamp: 1459854002,
+            base: "USD".to_owned(),
+            rates: rates,
+        };
+
+        assert_eq!(rate.bid_rates().rates["MYR"], 3.9);
+        assert_eq!(rate.ask_rates().rates["MYR"], 4.1);
+        assert_eq!(rate.mid_rates().rates["MYR"], 4.0);
+        assert_eq!(rate.mid_rates().timestamp, 1459854002);
+        assert!((rate.spreads_bps()["MYR"] - 500.0).abs() < 1e-9);
+        assert!(!rate.spreads_bps().contains_key("VEF"));
+    }
+}
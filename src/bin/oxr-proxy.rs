@@ -0,0 +1,608 @@
+//! A caching HTTP proxy for the OpenExchangeRates API, so that several services share one App ID
+//! and its quota.
+extern crate chrono;
+extern crate hyper;
+extern crate openexchangerates;
+extern crate serde;
+extern crate serde_json;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, Write};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::{NaiveDate, UTC};
+use hyper::method::Method;
+use hyper::server::{Handler, Request, Response, Server};
+use hyper::status::StatusCode;
+use hyper::uri::RequestUri;
+use openexchangerates::{CacheConfig, Client, Config, Currencies, ExchangeRate, Usage};
+use openexchangerates::error::Error;
+use openexchangerates::metrics::Metrics;
+use openexchangerates::provider::{CachingProvider, RateProvider};
+use openexchangerates::watcher;
+use serde::Serialize;
+
+const USAGE: &'static str = "Usage: oxr-proxy [options]
+
+Serves the /api/latest.json, /api/historical/<date>.json, /api/currencies.json and
+/api/usage.json endpoints from a cache, forwarding misses to OpenExchangeRates. The base and
+symbols parameters are applied by the proxy, so that every combination shares the cached rates.
+Metrics are served at /metrics.
+
+Options:
+    --listen <addr>     Address to listen on, defaults to 127.0.0.1:8080
+    --app-id <id>       App ID, defaults to the OXR_APP_ID environment variable; the other
+                        OXR_* variables are only read without this option
+    --ttl <seconds>     How long to keep the latest rates and those of the current date,
+                        defaults to OXR_CACHE_TTL or else the update interval of the plan
+    -h, --help          Show this message";
+
+/// Address to listen on without `--listen`.
+const DEFAULT_LISTEN: &'static str = "127.0.0.1:8080";
+
+/// How long to keep the latest rates if the update interval of the plan is unknown.
+const DEFAULT_TTL: u64 = 3600;
+
+/// Number of past dates to keep without `OXR_CACHE_CAPACITY`.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// How long to keep the usage statistics of the App ID.
+const USAGE_TTL: u64 = 60;
+
+const ENDPOINTS: &'static [&'static str] = &["latest", "historical", "currencies", "usage"];
+
+struct Args {
+    listen: String,
+    app_id: Option<String>,
+    ttl: Option<u64>,
+}
+
+fn parse_args<I>(args: I) -> Result<Args, String>
+    where I: IntoIterator<Item = String>
+{
+    let mut parsed = Args {
+        listen: DEFAULT_LISTEN.to_owned(),
+        app_id: None,
+        ttl: None,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "-h" | "--help" => return Err(USAGE.to_owned()),
+            "--listen" | "--app-id" | "--ttl" => {
+                let value = match args.next() {
+                    Some(value) => value,
+                    None => return Err(format!("missing value for `{}`", arg)),
+                };
+                match arg.as_ref() {
+                    "--listen" => parsed.listen = value,
+                    "--app-id" => parsed.app_id = Some(value),
+                    _ => {
+                        parsed.ttl = Some(try!(value.parse()
+                            .map_err(|_| format!("invalid TTL `{}`", value))))
+                    }
+                }
+            }
+            _ => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Requests answered by the proxy, an index into the counters of an endpoint.
+const SERVED: usize = 0;
+/// Requests forwarded to OpenExchangeRates.
+const UPSTREAM: usize = 1;
+/// Requests answered with an error.
+const ERRORS: usize = 2;
+
+/// The `SERVED`, `UPSTREAM` and `ERRORS` counters of every endpoint.
+type Counters = Arc<BTreeMap<&'static str, [AtomicUsize; 3]>>;
+
+fn count(counters: &Counters, endpoint: &str, counter: usize) {
+    if let Some(counts) = counters.get(endpoint) {
+        counts[counter].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The client, counting the requests that reach OpenExchangeRates.
+struct Upstream {
+    client: Client<'static>,
+    counters: Counters,
+}
+
+impl RateProvider for Upstream {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        count(&self.counters, "latest", UPSTREAM);
+        self.client.latest()
+    }
+
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        count(&self.counters, "historical", UPSTREAM);
+        self.client.historical(date)
+    }
+
+    fn currencies(&self) -> Result<Currencies, Error> {
+        count(&self.counters, "currencies", UPSTREAM);
+        self.client.currencies()
+    }
+}
+
+impl Upstream {
+    fn usage(&self) -> Result<Usage, Error> {
+        count(&self.counters, "usage", UPSTREAM);
+        self.client.usage()
+    }
+}
+
+/// A response of the proxy: status, content type and body.
+type Reply = (StatusCode, &'static str, String);
+
+struct Proxy {
+    cache: CachingProvider<Upstream>,
+    /// The historical rates of the current date, which change until the day ends.
+    today: Mutex<Option<(Instant, NaiveDate, ExchangeRate)>>,
+    usage: Mutex<Option<(Instant, Usage)>>,
+    counters: Counters,
+    metrics: Arc<Metrics>,
+    ttl: Duration,
+}
+
+impl Proxy {
+    /// Serve the responses of `client`, cached according to `cache`.
+    fn new(client: Client<'static>, cache: &CacheConfig) -> Proxy {
+        let metrics = Arc::new(Metrics::new());
+        let counters: Counters =
+            Arc::new(ENDPOINTS.iter().map(|endpoint| (*endpoint, Default::default())).collect());
+        let upstream = Upstream {
+            client: client.metrics(metrics.clone()),
+            counters: counters.clone(),
+        };
+        Proxy {
+            cache: CachingProvider::from_config(upstream, cache).metrics(metrics.clone()),
+            today: Mutex::new(None),
+            usage: Mutex::new(None),
+            counters: counters,
+            metrics: metrics,
+            ttl: Duration::from_secs(cache.ttl),
+        }
+    }
+
+    /// The usage statistics of the App ID, refreshed every `USAGE_TTL` seconds.
+    fn usage(&self) -> Result<Usage, Error> {
+        let mut slot = self.usage.lock().unwrap();
+        if let Some((ref fetched, ref usage)) = *slot {
+            if fetched.elapsed() < Duration::from_secs(USAGE_TTL) {
+                return Ok(usage.clone());
+            }
+        }
+        let usage = try!(self.cache.inner().usage());
+        *slot = Some((Instant::now(), usage.clone()));
+        Ok(usage)
+    }
+
+    /// Historical rates of past dates never change, so they are kept until evicted. Those of
+    /// the current date are kept for the time-to-live, like the latest rates.
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        if date < UTC::today().naive_utc() {
+            return self.cache.historical(date);
+        }
+
+        let mut slot = self.today.lock().unwrap();
+        if let Some((ref fetched, ref day, ref rate)) = *slot {
+            if *day == date && fetched.elapsed() < self.ttl {
+                self.metrics.cache("historical", true);
+                return Ok(rate.clone());
+            }
+        }
+        self.metrics.cache("historical", false);
+        let rate = try!(self.cache.inner().historical(date));
+        *slot = Some((Instant::now(), date, rate.clone()));
+        Ok(rate)
+    }
+
+    fn route(&self, uri: &str) -> Reply {
+        let (path, query) = match uri.find('?') {
+            Some(index) => (&uri[..index], &uri[index + 1..]),
+            None => (uri, ""),
+        };
+        if path == "/metrics" {
+            return (StatusCode::Ok, "text/plain; version=0.0.4", self.metrics());
+        }
+
+        let mut date = None;
+        let endpoint = match path {
+            "/api/latest.json" => "latest",
+            "/api/currencies.json" => "currencies",
+            "/api/usage.json" => "usage",
+            _ if path.starts_with("/api/historical/") && path.ends_with(".json") => {
+                let day = &path["/api/historical/".len()..path.len() - ".json".len()];
+                match NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+                    Ok(day) if day > UTC::today().naive_utc() => {
+                        return error(StatusCode::BadRequest, "invalid_date", "Date in the future")
+                    }
+                    Ok(day) => date = Some(day),
+                    Err(_) => return error(StatusCode::BadRequest, "invalid_date", "Invalid date"),
+                }
+                "historical"
+            }
+            _ => return error(StatusCode::NotFound, "not_found", "Unknown endpoint"),
+        };
+        count(&self.counters, endpoint, SERVED);
+
+        let reply = match (endpoint, date) {
+            (_, Some(date)) => self.historical(date).and_then(|rate| rates(&rate, query)),
+            ("latest", _) => self.cache.latest().and_then(|rate| rates(&rate, query)),
+            ("currencies", _) => self.cache.currencies().map(|currencies| to_json(&currencies)),
+            _ => self.usage().map(|usage| to_json(&usage)),
+        };
+        match reply {
+            Ok(body) => (StatusCode::Ok, "application/json", body),
+            Err(err) => {
+                count(&self.counters, endpoint, ERRORS);
+                match err {
+                    Error::MissingRate(_) => {
+                        error(StatusCode::BadRequest, "invalid_base", &err.to_string())
+                    }
+                    Error::Gap(_) => error(StatusCode::NotFound, "not_available", &err.to_string()),
+                    _ => error(StatusCode::BadGateway, "upstream_error", &err.to_string()),
+                }
+            }
+        }
+    }
+
+    /// The counters of the proxy and of its client, and the quota of the App ID, in the
+    /// Prometheus text format.
+    fn metrics(&self) -> String {
+        let mut out = String::new();
+        let counters = [("requests_total", "Requests answered by the proxy.", SERVED),
+                        ("upstream_requests_total",
+                         "Requests forwarded to OpenExchangeRates.",
+                         UPSTREAM),
+                        ("errors_total", "Requests answered with an error.", ERRORS)];
+        for &(name, help, counter) in &counters {
+            out.push_str(&format!("# HELP oxr_proxy_{} {}\n# TYPE oxr_proxy_{} counter\n",
+                                  name,
+                                  help,
+                                  name));
+            for (endpoint, counts) in self.counters.iter() {
+                out.push_str(&format!("oxr_proxy_{}{{endpoint=\"{}\"}} {}\n",
+                                      name,
+                                      endpoint,
+                                      counts[counter].load(Ordering::Relaxed)));
+            }
+        }
+
+        out.push_str("# HELP oxr_proxy_cache_hit_ratio Share of cache lookups that were \
+                      hits.\n# TYPE oxr_proxy_cache_hit_ratio gauge\n");
+        for endpoint in self.counters.keys() {
+            let (hits, misses) = self.metrics.cache_lookups(endpoint);
+            if hits + misses > 0 {
+                out.push_str(&format!("oxr_proxy_cache_hit_ratio{{endpoint=\"{}\"}} {}\n",
+                                      endpoint,
+                                      hits as f64 / (hits + misses) as f64));
+            }
+        }
+
+        out.push_str(&format!("# HELP oxr_proxy_cache_ttl_seconds How long the latest rates are \
+                               kept.\n# TYPE oxr_proxy_cache_ttl_seconds gauge\n\
+                               oxr_proxy_cache_ttl_seconds {}\n",
+                              self.ttl.as_secs()));
+
+        if let Ok(usage) = self.usage() {
+            let usage = &usage.data.usage;
+            let gauges = [("upstream_requests", "Requests made this period.", usage.requests),
+                          ("upstream_requests_quota",
+                           "Requests allowed this period.",
+                           usage.requests_quota),
+                          ("upstream_requests_remaining",
+                           "Requests left this period.",
+                           usage.requests_remaining),
+                          ("upstream_days_remaining",
+                           "Days left in this period.",
+                           usage.days_remaining)];
+            for &(name, help, value) in &gauges {
+                out.push_str(&format!("# HELP oxr_proxy_{} {}\n# TYPE oxr_proxy_{} gauge\n\
+                                       oxr_proxy_{} {}\n",
+                                      name,
+                                      help,
+                                      name,
+                                      name,
+                                      value));
+            }
+        }
+        out.push_str(&self.metrics.render());
+        out
+    }
+}
+
+impl Handler for Proxy {
+    fn handle(&self, req: Request, mut res: Response) {
+        let (status, content_type, body) = match (&req.method, &req.uri) {
+            (&Method::Get, &RequestUri::AbsolutePath(ref uri)) => self.route(uri),
+            (&Method::Get, _) => error(StatusCode::NotFound, "not_found", "Unknown endpoint"),
+            _ => error(StatusCode::MethodNotAllowed, "method_not_allowed", "Only GET is allowed"),
+        };
+        *res.status_mut() = status;
+        res.headers_mut().set_raw("Content-Type", vec![content_type.as_bytes().to_vec()]);
+        if let Err(err) = res.send(body.as_bytes()) {
+            let _ = writeln!(io::stderr(), "failed to send response: {}", err);
+        }
+    }
+}
+
+/// Rebase `rate` and keep the requested symbols, as the `base` and `symbols` parameters of the
+/// API do, in whichever order they are given.
+fn rates(rate: &ExchangeRate, query: &str) -> Result<String, Error> {
+    let mut base = None;
+    let mut symbols = None;
+    for param in query.split('&') {
+        let (key, value) = match param.find('=') {
+            Some(index) => (&param[..index], &param[index + 1..]),
+            None => (param, ""),
+        };
+        match key {
+            "base" if !value.is_empty() => base = Some(value.to_uppercase()),
+            "symbols" if !value.is_empty() => {
+                symbols = Some(value.replace("%2C", ",")
+                    .replace("%2c", ",")
+                    .split(',')
+                    .map(|symbol| symbol.trim().to_uppercase())
+                    .collect::<Vec<String>>())
+            }
+            _ => {}
+        }
+    }
+
+    let mut rate = match base {
+        Some(ref base) if *base != rate.base => try!(rate.rebase(base)),
+        _ => rate.clone(),
+    };
+    if let Some(symbols) = symbols {
+        rate.rates = rate.rates
+            .into_iter()
+            .filter(|&(ref currency, _)| symbols.contains(currency))
+            .collect();
+    }
+    Ok(to_json(&rate))
+}
+
+fn to_json<T>(value: &T) -> String
+    where T: Serialize
+{
+    serde_json::to_string(value).expect("response types always serialize")
+}
+
+/// An error in the format of the API.
+fn error(status: StatusCode, message: &str, description: &str) -> Reply {
+    let mut object = BTreeMap::new();
+    object.insert("error", serde_json::Value::Bool(true));
+    object.insert("status", serde_json::Value::U64(status.to_u16() as u64));
+    object.insert("message", serde_json::Value::String(message.to_owned()));
+    object.insert("description", serde_json::Value::String(description.to_owned()));
+    (status, "application/json", to_json(&object))
+}
+
+/// The update interval of the plan, or `DEFAULT_TTL` if it cannot be read.
+fn plan_ttl(client: &Client) -> u64 {
+    match client.usage() {
+        Ok(usage) => {
+            watcher::update_interval(&usage.data.plan.update_frequency)
+                .map_or(DEFAULT_TTL, |interval| interval.as_secs())
+        }
+        Err(err) => {
+            let _ = writeln!(io::stderr(),
+                             "cannot read the plan ({}), keeping rates for {} seconds",
+                             err,
+                             DEFAULT_TTL);
+            DEFAULT_TTL
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let config = match args.app_id {
+        Some(ref app_id) => {
+            Config {
+                app_id: app_id.clone(),
+                base_url: None,
+                timeout: None,
+                base: None,
+                max_age: None,
+                cache: None,
+            }
+        }
+        None => try!(Config::from_env().map_err(|err| err.to_string())),
+    };
+    let client = try!(Client::from_config(&config).map_err(|err| err.to_string()));
+
+    let ttl = match (args.ttl, config.cache.as_ref()) {
+        (Some(ttl), _) => ttl,
+        (None, Some(cache)) => cache.ttl,
+        (None, None) => plan_ttl(&client),
+    };
+    let cache = CacheConfig {
+        ttl: ttl,
+        capacity: Some(config.cache
+            .as_ref()
+            .and_then(|cache| cache.capacity)
+            .unwrap_or(DEFAULT_CAPACITY)),
+    };
+    let proxy = Proxy::new(client, &cache);
+
+    let server = try!(Server::http(&args.listen[..])
+        .map_err(|err| format!("cannot listen on {}: {}", args.listen, err)));
+    let _ = writeln!(io::stderr(),
+                     "listening on {}, keeping the latest rates for {} seconds",
+                     args.listen,
+                     ttl);
+    try!(server.handle(proxy).map_err(|err| err.to_string()));
+    Ok(())
+}
+
+fn main() {
+    if let Err(msg) = parse_args(env::args().skip(1)).and_then(run) {
+        let _ = writeln!(io::stderr(), "{}", msg);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
+
+    use chrono::UTC;
+    use hyper::status::StatusCode;
+    use openexchangerates::{CacheConfig, Client, ExchangeRate};
+    use openexchangerates::error::Error;
+    use serde_json;
+
+    use super::{DEFAULT_LISTEN, Proxy, SERVED, UPSTREAM, USAGE, parse_args, rates};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn rate() -> ExchangeRate {
+        ExchangeRate {
+            disclaimer: String::new(),
+            license: String::new(),
+            timestamp: 1459854002,
+            base: "USD".to_owned(),
+            rates: vec![("EUR".to_owned(), 0.8), ("MYR".to_owned(), 4.0), ("USD".to_owned(), 1.0)]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// A proxy whose upstream refuses every connection.
+    fn proxy() -> Proxy {
+        let client = Client::new("1234").base_url("http://127.0.0.1:1/api");
+        Proxy::new(client,
+                   &CacheConfig {
+                       ttl: 60,
+                       capacity: None,
+                   })
+    }
+
+    fn count(proxy: &Proxy, endpoint: &str, counter: usize) -> usize {
+        proxy.counters[endpoint][counter].load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn parses_options() {
+        let parsed = parse_args(args(&[])).unwrap();
+        assert_eq!(parsed.listen, DEFAULT_LISTEN);
+        assert_eq!(parsed.app_id, None);
+        assert_eq!(parsed.ttl, None);
+
+        let parsed = parse_args(args(&["--ttl", "600", "--listen", "0.0.0.0:80", "--app-id",
+                                       "1234"]))
+            .unwrap();
+        assert_eq!(parsed.listen, "0.0.0.0:80");
+        assert_eq!(parsed.app_id, Some("1234".to_owned()));
+        assert_eq!(parsed.ttl, Some(600));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(parse_args(args(&["-h"])).err(), Some(USAGE.to_owned()));
+        assert_eq!(parse_args(args(&["--ttl"])).err(),
+                   Some("missing value for `--ttl`".to_owned()));
+        assert_eq!(parse_args(args(&["--ttl", "hour"])).err(),
+                   Some("invalid TTL `hour`".to_owned()));
+        assert_eq!(parse_args(args(&["--port", "80"])).err(),
+                   Some("unknown argument `--port`".to_owned()));
+    }
+
+    #[test]
+    fn rebases_then_filters_in_any_order() {
+        for query in &["base=EUR&symbols=MYR,USD", "symbols=MYR,USD&base=EUR",
+                       "symbols=myr%2Cusd&base=eur"] {
+            let rate: ExchangeRate = serde_json::from_str(&rates(&rate(), query).unwrap())
+                .unwrap();
+            assert_eq!(rate.base, "EUR", "{}", query);
+            assert_eq!(rate.rates.len(), 2, "{}", query);
+            assert_eq!(rate.rates["MYR"], 5.0, "{}", query);
+            assert_eq!(rate.rates["USD"], 1.25, "{}", query);
+        }
+
+        let rate: ExchangeRate = serde_json::from_str(&rates(&rate(), "").unwrap()).unwrap();
+        assert_eq!(rate, self::rate());
+        match rates(&self::rate(), "symbols=MYR&base=XXX") {
+            Err(Error::MissingRate(currency)) => assert_eq!(currency, "XXX"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_routes_and_bad_dates() {
+        let proxy = proxy();
+        let tomorrow = UTC::today().naive_utc().succ();
+        let routes = [("/api/convert.json", StatusCode::NotFound, "not_found"),
+                      ("/latest.json", StatusCode::NotFound, "not_found"),
+                      ("/api/historical/2016-02-30.json", StatusCode::BadRequest, "invalid_date"),
+                      ("/api/historical/latest.json", StatusCode::BadRequest, "invalid_date"),
+                      (&format!("/api/historical/{}.json", tomorrow),
+                       StatusCode::BadRequest,
+                       "invalid_date")];
+        for &(uri, status, message) in &routes {
+            let (actual, content_type, body) = proxy.route(uri);
+            assert_eq!(actual, status, "{}", uri);
+            assert_eq!(content_type, "application/json");
+            assert!(body.contains(&format!("\"message\":\"{}\"", message)), "{}", body);
+        }
+        assert_eq!(count(&proxy, "historical", SERVED), 0);
+        assert_eq!(count(&proxy, "historical", UPSTREAM), 0);
+    }
+
+    #[test]
+    fn caches_today_for_the_ttl() {
+        let proxy = proxy();
+        let today = UTC::today().naive_utc();
+        let uri = format!("/api/historical/{}.json?symbols=MYR", today);
+
+        *proxy.today.lock().unwrap() = Some((Instant::now(), today, rate()));
+        let (status, _, body) = proxy.route(&uri);
+        assert_eq!(status, StatusCode::Ok);
+        let served: ExchangeRate = serde_json::from_str(&body).unwrap();
+        assert_eq!(served.rates.keys().collect::<Vec<_>>(), vec!["MYR"]);
+        assert_eq!(count(&proxy, "historical", UPSTREAM), 0);
+
+        let expired = Instant::now() - Duration::from_secs(120);
+        *proxy.today.lock().unwrap() = Some((expired, today, rate()));
+        assert_eq!(proxy.route(&uri).0, StatusCode::BadGateway);
+        assert_eq!(count(&proxy, "historical", SERVED), 2);
+        assert_eq!(count(&proxy, "historical", UPSTREAM), 1);
+    }
+
+    #[test]
+    fn hit_ratio_counts_cache_lookups_only() {
+        let proxy = proxy();
+        let today = UTC::today().naive_utc();
+        let uri = format!("/api/historical/{}.json", today);
+
+        *proxy.today.lock().unwrap() = Some((Instant::now(), today, rate()));
+        assert_eq!(proxy.route(&format!("{}?base=XYZ", uri)).0, StatusCode::BadRequest);
+        assert_eq!(proxy.route(&uri).0, StatusCode::Ok);
+        let expired = Instant::now() - Duration::from_secs(120);
+        *proxy.today.lock().unwrap() = Some((expired, today, rate()));
+        assert_eq!(proxy.route(&uri).0, StatusCode::BadGateway);
+        assert_eq!(proxy.route(&format!("/api/historical/{}.json", today.succ())).0,
+                   StatusCode::BadRequest);
+
+        let out = proxy.metrics();
+        let ratio = out.lines()
+            .filter(|line| line.starts_with("oxr_proxy_cache_hit_ratio{"))
+            .collect::<Vec<_>>();
+        assert_eq!(ratio,
+                   vec!["oxr_proxy_cache_hit_ratio{endpoint=\"historical\"} \
+                         0.6666666666666666"]);
+    }
+}
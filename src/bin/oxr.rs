@@ -0,0 +1,403 @@
+//! Command-line access to the OpenExchangeRates API.
+extern crate chrono;
+extern crate openexchangerates;
+extern crate serde;
+extern crate serde_json;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use chrono::NaiveDate;
+use openexchangerates::{Client, Currencies, ExchangeRate, Usage};
+use openexchangerates::csv;
+use openexchangerates::error::Error;
+use serde::Serialize;
+use serde_json::Value;
+
+const USAGE: &'static str = "Usage: oxr [options] <command> [<args>...]
+
+Commands:
+    latest                          Latest exchange rates
+    historical <date>               Exchange rates at the end of <date> (YYYY-MM-DD)
+    currencies                      Supported currencies
+    usage                           Statistics about the App ID
+    convert <amount> <from> <to>    Convert <amount> of <from> into <to> at the latest rates
+    timeseries <start> <end>        Exchange rates for every date from <start> to <end>, in
+                                    one request (requires a plan with time-series)
+
+Options:
+    --app-id <id>       App ID, defaults to the OXR_APP_ID environment variable
+    --base <code>       Base currency of the exchange rates
+    --symbols <codes>   Comma separated currencies to include
+    --format <format>   Output format: table (default), json or csv
+    -h, --help          Show this message";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+struct Args {
+    app_id: Option<String>,
+    base: Option<String>,
+    symbols: Option<String>,
+    format: Format,
+    help: bool,
+    command: Vec<String>,
+}
+
+fn parse_args<I>(args: I) -> Result<Args, String>
+    where I: IntoIterator<Item = String>
+{
+    let mut parsed = Args {
+        app_id: None,
+        base: None,
+        symbols: None,
+        format: Format::Table,
+        help: false,
+        command: Vec::new(),
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "-h" | "--help" => {
+                parsed.help = true;
+                return Ok(parsed);
+            }
+            "--app-id" | "--base" | "--symbols" | "--format" => {
+                let value = match args.next() {
+                    Some(value) => value,
+                    None => return Err(format!("missing value for `{}`", arg)),
+                };
+                match arg.as_ref() {
+                    "--app-id" => parsed.app_id = Some(value),
+                    "--base" => parsed.base = Some(value.to_uppercase()),
+                    "--symbols" => parsed.symbols = Some(value.to_uppercase()),
+                    _ => {
+                        parsed.format = match value.as_ref() {
+                            "table" => Format::Table,
+                            "json" => Format::Json,
+                            "csv" => Format::Csv,
+                            _ => return Err(format!("unknown format `{}`", value)),
+                        }
+                    }
+                }
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ => parsed.command.push(arg),
+        }
+    }
+
+    if parsed.command.is_empty() {
+        return Err(USAGE.to_owned());
+    }
+    Ok(parsed)
+}
+
+fn client(args: &Args) -> Result<Client<'static>, Error> {
+    let mut client = match args.app_id {
+        Some(ref app_id) => Client::new(app_id.clone()),
+        None => try!(Client::from_env()),
+    };
+    if let Some(ref base) = args.base {
+        client = client.base(base.clone());
+    }
+    if let Some(ref symbols) = args.symbols {
+        client = client.symbols(symbols.split(','));
+    }
+    Ok(client)
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| format!("invalid date `{}`", s))
+}
+
+fn run(args: Args) -> Result<String, String> {
+    if args.help {
+        return Ok(format!("{}\n", USAGE));
+    }
+    let client = try!(client(&args).map_err(|err| err.to_string()));
+    let params = &args.command[1..];
+
+    match (args.command[0].as_ref(), params.len()) {
+        ("latest", 0) => {
+            let rate = try!(client.latest().map_err(|err| err.to_string()));
+            Ok(print_rate(&rate, args.format))
+        }
+        ("historical", 1) => {
+            let date = try!(parse_date(&params[0]));
+            let rate = try!(client.historical(date).map_err(|err| err.to_string()));
+            Ok(print_rate(&rate, args.format))
+        }
+        ("currencies", 0) => {
+            let currencies = try!(client.currencies().map_err(|err| err.to_string()));
+            Ok(print_currencies(&currencies, args.format))
+        }
+        ("usage", 0) => {
+            let usage = try!(client.usage().map_err(|err| err.to_string()));
+            Ok(print_usage(&usage, args.format))
+        }
+        ("convert", 3) => {
+            let amount: f64 = try!(params[0]
+                .parse()
+                .map_err(|_| format!("invalid amount `{}`", params[0])));
+            let (from, to) = (params[1].to_uppercase(), params[2].to_uppercase());
+            let rate = try!(client.latest().map_err(|err| err.to_string()));
+            match rate.convert(amount, &from, &to) {
+                Some(result) => Ok(print_conversion(amount, &from, &to, result, args.format)),
+                None => Err(format!("no exchange rate between {} and {}", from, to)),
+            }
+        }
+        ("timeseries", 2) => {
+            let (start, end) = (try!(parse_date(&params[0])), try!(parse_date(&params[1])));
+            if start > end {
+                return Err("start date is after end date".to_owned());
+            }
+            let series = try!(client.time_series(start, end).map_err(|err| err.to_string()));
+            let mut rates = Vec::new();
+            for rate in series {
+                rates.push(try!(rate.map_err(|err| err.to_string())));
+            }
+            Ok(print_series(&rates, args.format))
+        }
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+fn to_json<T>(value: &T) -> String
+    where T: Serialize
+{
+    serde_json::to_string_pretty(value).expect("response types always serialize") + "\n"
+}
+
+/// Quote a CSV field if needed.
+fn csv_field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn print_rate(rate: &ExchangeRate, format: Format) -> String {
+    match format {
+        Format::Table => {
+            let mut out = format!("Base: {}\nTime: {}\n", rate.base, rate.datetime());
+            for (currency, value) in &rate.rates {
+                out.push_str(&format!("{:<6}{:>20}\n", currency, value));
+            }
+            out
+        }
+        Format::Json => to_json(rate),
+        Format::Csv => {
+            let mut out = "currency,rate\n".to_owned();
+            for (currency, value) in &rate.rates {
+                out.push_str(&format!("{},{}\n", currency, value));
+            }
+            out
+        }
+    }
+}
+
+fn print_currencies(currencies: &Currencies, format: Format) -> String {
+    match format {
+        Format::Table => {
+            currencies.iter()
+                .map(|(code, name)| format!("{:<6}{}\n", code, name))
+                .collect()
+        }
+        Format::Json => to_json(currencies),
+        Format::Csv => {
+            let mut out = "code,name\n".to_owned();
+            for (code, name) in currencies {
+                out.push_str(&format!("{},{}\n", code, csv_field(name)));
+            }
+            out
+        }
+    }
+}
+
+fn print_usage(usage: &Usage, format: Format) -> String {
+    let data = &usage.data;
+    let fields = vec![("app_id", data.app_id.clone()),
+                      ("status", data.status.clone()),
+                      ("plan", data.plan.name.clone()),
+                      ("quota", data.plan.quota.clone()),
+                      ("update_frequency", data.plan.update_frequency.clone()),
+                      ("requests", data.usage.requests.to_string()),
+                      ("requests_quota", data.usage.requests_quota.to_string()),
+                      ("requests_remaining", data.usage.requests_remaining.to_string()),
+                      ("days_elapsed", data.usage.days_elapsed.to_string()),
+                      ("days_remaining", data.usage.days_remaining.to_string()),
+                      ("daily_average", data.usage.daily_average.to_string())];
+    match format {
+        Format::Table => {
+            fields.iter()
+                .map(|&(key, ref value)| format!("{:<20}{}\n", key, value))
+                .collect()
+        }
+        Format::Json => to_json(usage),
+        Format::Csv => {
+            let mut out = "key,value\n".to_owned();
+            for (key, value) in fields {
+                out.push_str(&format!("{},{}\n", key, csv_field(&value)));
+            }
+            out
+        }
+    }
+}
+
+fn print_conversion(amount: f64, from: &str, to: &str, result: f64, format: Format) -> String {
+    match format {
+        Format::Table => format!("{} {} = {} {}\n", amount, from, result, to),
+        Format::Json => {
+            let mut object = BTreeMap::new();
+            object.insert("amount", Value::F64(amount));
+            object.insert("from", Value::String(from.to_owned()));
+            object.insert("to", Value::String(to.to_owned()));
+            object.insert("result", Value::F64(result));
+            to_json(&object)
+        }
+        Format::Csv => format!("amount,from,to,result\n{},{},{},{}\n", amount, from, to, result),
+    }
+}
+
+fn print_series(rates: &[ExchangeRate], format: Format) -> String {
+    match format {
+        Format::Table => {
+            rates.iter()
+                .map(|rate| {
+                    let values: Vec<String> = rate.rates
+                        .iter()
+                        .map(|(currency, value)| format!("{}={}", currency, value))
+                        .collect();
+                    format!("{}  {}  {}\n", rate.date(), rate.base, values.join(" "))
+                })
+                .collect()
+        }
+        Format::Json => to_json(&rates),
+        Format::Csv => {
+            let mut out = Vec::new();
+            csv::write_long(&mut out, rates).expect("writing to memory cannot fail");
+            String::from_utf8(out).expect("CSV output is UTF-8")
+        }
+    }
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(run);
+    match result {
+        Ok(out) => print!("{}", out),
+        Err(msg) => {
+            let _ = writeln!(io::stderr(), "{}", msg);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use openexchangerates::ExchangeRate;
+
+    use super::{Format, USAGE, parse_args, print_conversion, print_currencies, print_rate,
+                print_series, run};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn rate(timestamp: i64) -> ExchangeRate {
+        let mut rates = BTreeMap::new();
+        rates.insert("MYR".to_owned(), 3.9);
+        rates.insert("SGD".to_owned(), 1.35);
+        ExchangeRate {
+            disclaimer: String::new(),
+            license: String::new(),
+            timestamp: timestamp,
+            base: "USD".to_owned(),
+            rates: rates,
+        }
+    }
+
+    #[test]
+    fn parses_options_and_command() {
+        let parsed = parse_args(args(&["--base", "eur", "--symbols", "myr,sgd", "--format",
+                                       "csv", "historical", "2016-04-05"]))
+            .unwrap();
+        assert_eq!(parsed.app_id, None);
+        assert_eq!(parsed.base, Some("EUR".to_owned()));
+        assert_eq!(parsed.symbols, Some("MYR,SGD".to_owned()));
+        assert!(parsed.format == Format::Csv);
+        assert_eq!(parsed.command, args(&["historical", "2016-04-05"]));
+
+        let parsed = parse_args(args(&["latest", "--app-id", "1234"])).unwrap();
+        assert_eq!(parsed.app_id, Some("1234".to_owned()));
+        assert!(parsed.format == Format::Table);
+    }
+
+    #[test]
+    fn prints_help() {
+        let parsed = parse_args(args(&["--help", "latest"])).unwrap();
+        assert!(parsed.help);
+        assert_eq!(run(parsed), Ok(format!("{}\n", USAGE)));
+        assert!(parse_args(args(&["-h"])).unwrap().help);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(parse_args(args(&[])).err(), Some(USAGE.to_owned()));
+        assert_eq!(parse_args(args(&["latest", "--base"])).err(),
+                   Some("missing value for `--base`".to_owned()));
+        assert_eq!(parse_args(args(&["--format", "xml", "latest"])).err(),
+                   Some("unknown format `xml`".to_owned()));
+        assert_eq!(parse_args(args(&["--verbose", "latest"])).err(),
+                   Some("unknown option `--verbose`".to_owned()));
+    }
+
+    #[test]
+    fn prints_rates() {
+        assert_eq!(print_rate(&rate(1459854002), Format::Table),
+                   "Base: USD\nTime: 2016-04-05 11:00:02 UTC\n\
+                    MYR                    3.9\n\
+                    SGD                   1.35\n");
+        assert_eq!(print_rate(&rate(1459854002), Format::Csv),
+                   "currency,rate\nMYR,3.9\nSGD,1.35\n");
+        assert!(print_rate(&rate(1459854002), Format::Json).ends_with("}\n"));
+    }
+
+    #[test]
+    fn prints_currencies() {
+        let mut currencies = BTreeMap::new();
+        currencies.insert("MYR".to_owned(), "Malaysian Ringgit".to_owned());
+        currencies.insert("XYZ".to_owned(), "Dollar, \"New\"".to_owned());
+        assert_eq!(print_currencies(&currencies, Format::Table),
+                   "MYR   Malaysian Ringgit\nXYZ   Dollar, \"New\"\n");
+        assert_eq!(print_currencies(&currencies, Format::Csv),
+                   "code,name\nMYR,Malaysian Ringgit\nXYZ,\"Dollar, \"\"New\"\"\"\n");
+    }
+
+    #[test]
+    fn prints_conversion() {
+        assert_eq!(print_conversion(100.0, "USD", "MYR", 390.0, Format::Table),
+                   "100 USD = 390 MYR\n");
+        assert_eq!(print_conversion(100.0, "USD", "MYR", 390.0, Format::Csv),
+                   "amount,from,to,result\n100,USD,MYR,390\n");
+    }
+
+    #[test]
+    fn prints_series() {
+        let rates = vec![rate(1459728000), rate(1459814400)];
+        assert_eq!(print_series(&rates, Format::Table),
+                   "2016-04-04  USD  MYR=3.9 SGD=1.35\n2016-04-05  USD  MYR=3.9 SGD=1.35\n");
+        assert_eq!(print_series(&rates, Format::Csv),
+                   "date,base,currency,rate\n2016-04-04,USD,MYR,3.9\n2016-04-04,USD,SGD,1.35\n\
+                    2016-04-05,USD,MYR,3.9\n2016-04-05,USD,SGD,1.35\n");
+    }
+}
@@ -0,0 +1,300 @@
+//! Calendar module
+//!
+//! Business-day calendars and gap filling for series of daily snapshots, such as results of
+//! `Client::historical` that skip weekends or failed requests.
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use ExchangeRate;
+use error::Error;
+
+/// Days on which markets are open: every day except the weekend and holidays.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BusinessCalendar {
+    weekend: Vec<Weekday>,
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl Default for BusinessCalendar {
+    fn default() -> BusinessCalendar {
+        BusinessCalendar::new()
+    }
+}
+
+impl BusinessCalendar {
+    /// A calendar with Saturday and Sunday as weekend and no holidays.
+    pub fn new() -> BusinessCalendar {
+        BusinessCalendar {
+            weekend: vec![Weekday::Sat, Weekday::Sun],
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    /// Use `days` as weekend instead, e.g. Friday and Saturday.
+    pub fn weekend(mut self, days: &[Weekday]) -> BusinessCalendar {
+        self.weekend = days.to_vec();
+        self
+    }
+
+    /// Add a holiday.
+    pub fn holiday(mut self, date: NaiveDate) -> BusinessCalendar {
+        self.holidays.insert(date);
+        self
+    }
+
+    /// Add several holidays.
+    pub fn holidays<I>(mut self, dates: I) -> BusinessCalendar
+        where I: IntoIterator<Item = NaiveDate>
+    {
+        self.holidays.extend(dates);
+        self
+    }
+
+    /// Add the holidays listed in `s`, one `YYYY-MM-DD` date per line. Blank lines and lines
+    /// starting with `#` are ignored.
+    pub fn parse_holidays(self, s: &str) -> Result<BusinessCalendar, Error> {
+        let mut dates = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match NaiveDate::parse_from_str(line, "%Y-%m-%d") {
+                Ok(date) => dates.push(date),
+                Err(_) => {
+                    return Err(Error::Config(format!("line {}: invalid holiday `{}`",
+                                                     index + 1,
+                                                     line)))
+                }
+            }
+        }
+        Ok(self.holidays(dates))
+    }
+
+    /// Whether `date` is neither on the weekend nor a holiday.
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !self.weekend.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+
+    /// The business days from `start` to `end`, both included.
+    pub fn business_days(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        days(start, end).into_iter().filter(|&date| self.is_business_day(date)).collect()
+    }
+
+    /// The snapshots of `rates` that fall on business days.
+    pub fn restrict(&self, rates: &[ExchangeRate]) -> Vec<ExchangeRate> {
+        rates.iter().filter(|rate| self.is_business_day(rate.date())).cloned().collect()
+    }
+}
+
+/// How to fill the dates missing from a series.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillPolicy {
+    /// Repeat the previous snapshot.
+    ForwardFill,
+    /// Interpolate linearly between the previous and the next snapshot. Currencies missing
+    /// from either are left out.
+    Interpolate,
+    /// Fail with `Error::Gap`.
+    Error,
+}
+
+/// The dates between the first and the last snapshot of `rates` without a snapshot, limited to
+/// the business days of `calendar` if given.
+pub fn missing_dates(rates: &[ExchangeRate],
+                     calendar: Option<&BusinessCalendar>)
+                     -> Vec<NaiveDate> {
+    let present: BTreeSet<NaiveDate> = rates.iter().map(|rate| rate.date()).collect();
+    match (present.iter().next(), present.iter().next_back()) {
+        (Some(&start), Some(&end)) => {
+            expected_days(start, end, calendar)
+                .into_iter()
+                .filter(|date| !present.contains(date))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Turn `rates` into one snapshot per day from the first to the last snapshot, or per business
+/// day of `calendar` if given, filling missing dates according to `policy`.
+///
+/// Snapshots on other days are dropped and, when a date has several, the latest one is kept.
+/// Filled snapshots keep the time of day of the snapshot they are derived from.
+pub fn fill(rates: &[ExchangeRate],
+            policy: FillPolicy,
+            calendar: Option<&BusinessCalendar>)
+            -> Result<Vec<ExchangeRate>, Error> {
+    let mut snapshots: Vec<&ExchangeRate> = rates.iter()
+        .filter(|rate| calendar.map_or(true, |calendar| calendar.is_business_day(rate.date())))
+        .collect();
+    snapshots.sort_by_key(|rate| rate.timestamp);
+    let mut unique: Vec<&ExchangeRate> = Vec::with_capacity(snapshots.len());
+    for rate in snapshots {
+        if unique.last().map(|last| last.date()) == Some(rate.date()) {
+            unique.pop();
+        }
+        unique.push(rate);
+    }
+
+    let (start, end) = match (unique.first(), unique.last()) {
+        (Some(first), Some(last)) => (first.date(), last.date()),
+        _ => return Ok(Vec::new()),
+    };
+
+    let mut filled = Vec::new();
+    let mut next = 0;
+    for date in expected_days(start, end, calendar) {
+        if unique[next].date() == date {
+            filled.push(unique[next].clone());
+            next += 1;
+            continue;
+        }
+
+        let (previous, following) = (unique[next - 1], unique[next]);
+        match policy {
+            FillPolicy::ForwardFill => filled.push(shift(previous, date)),
+            FillPolicy::Interpolate => filled.push(try!(interpolate(previous, following, date))),
+            FillPolicy::Error => return Err(Error::Gap(date)),
+        }
+    }
+    Ok(filled)
+}
+
+fn days(start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+    let mut days = Vec::new();
+    let mut date = start;
+    while date <= end {
+        days.push(date);
+        date = date + Duration::days(1);
+    }
+    days
+}
+
+fn expected_days(start: NaiveDate,
+                 end: NaiveDate,
+                 calendar: Option<&BusinessCalendar>)
+                 -> Vec<NaiveDate> {
+    match calendar {
+        Some(calendar) => calendar.business_days(start, end),
+        None => days(start, end),
+    }
+}
+
+/// A copy of `rate` moved to `date`.
+fn shift(rate: &ExchangeRate, date: NaiveDate) -> ExchangeRate {
+    let mut shifted = rate.clone();
+    shifted.timestamp += (date - rate.date()).num_days() * 86400;
+    shifted
+}
+
+fn interpolate(previous: &ExchangeRate,
+               following: &ExchangeRate,
+               date: NaiveDate)
+               -> Result<ExchangeRate, Error> {
+    let rebased;
+    let following = if following.base != previous.base {
+        rebased = try!(following.rebase(&previous.base));
+        &rebased
+    } else {
+        following
+    };
+
+    let span = (following.date() - previous.date()).num_days() as f64;
+    let weight = (date - previous.date()).num_days() as f64 / span;
+
+    let mut interpolated = shift(previous, date);
+    interpolated.rates = previous.rates
+        .iter()
+        .filter_map(|(currency, &start)| {
+            following.rates
+                .get(currency)
+                .map(|&end| (currency.clone(), start + (end - start) * weight))
+        })
+        .collect();
+    Ok(interpolated)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Weekday};
+
+    use ExchangeRate;
+    use error::Error;
+    use super::*;
+
+    /// A snapshot at 23:00 UTC on the given day of April 2016, which starts on a Friday.
+    fn rate(day: u32, myr: f64) -> ExchangeRate {
+        let timestamp = NaiveDate::from_ymd(2016, 4, day).and_hms(23, 0, 0).timestamp();
+        ExchangeRate::fixture(timestamp, "USD", &[("MYR", myr)])
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2016, 4, day)
+    }
+
+    #[test]
+    fn business_days() {
+        let calendar = BusinessCalendar::new().holiday(date(5));
+        assert!(calendar.is_business_day(date(4)));
+        assert!(!calendar.is_business_day(date(2)));
+        assert!(!calendar.is_business_day(date(5)));
+        assert_eq!(calendar.business_days(date(1), date(6)),
+                   vec![date(1), date(4), date(6)]);
+
+        let calendar = BusinessCalendar::new().weekend(&[Weekday::Fri, Weekday::Sat]);
+        assert_eq!(calendar.business_days(date(1), date(3)), vec![date(3)]);
+    }
+
+    #[test]
+    fn parse_holidays() {
+        let calendar = BusinessCalendar::new()
+            .parse_holidays("# Malaysia\n2016-04-04\n\n2016-04-05\n")
+            .unwrap();
+        assert_eq!(calendar.business_days(date(4), date(6)), vec![date(6)]);
+
+        assert!(BusinessCalendar::new().parse_holidays("2016-04-31").is_err());
+    }
+
+    #[test]
+    fn restrict() {
+        let rates = vec![rate(1, 4.0), rate(2, 4.1), rate(4, 4.2)];
+        let restricted = BusinessCalendar::new().restrict(&rates);
+        assert_eq!(restricted, vec![rate(1, 4.0), rate(4, 4.2)]);
+    }
+
+    #[test]
+    fn missing() {
+        let rates = vec![rate(1, 4.0), rate(5, 4.4)];
+        assert_eq!(missing_dates(&rates, None), vec![date(2), date(3), date(4)]);
+        assert_eq!(missing_dates(&rates, Some(&BusinessCalendar::new())), vec![date(4)]);
+    }
+
+    #[test]
+    fn forward_fill() {
+        let rates = vec![rate(4, 4.2), rate(1, 4.0)];
+        let filled = fill(&rates, FillPolicy::ForwardFill, None).unwrap();
+        assert_eq!(filled, vec![rate(1, 4.0), rate(2, 4.0), rate(3, 4.0), rate(4, 4.2)]);
+    }
+
+    #[test]
+    fn interpolate() {
+        let rates = vec![rate(1, 4.0), rate(5, 4.4)];
+        let filled = fill(&rates, FillPolicy::Interpolate, Some(&BusinessCalendar::new()))
+            .unwrap();
+        assert_eq!(filled.len(), 3);
+        assert_eq!(filled[1].date(), date(4));
+        assert!((filled[1].rates["MYR"] - 4.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn error_on_gap() {
+        let rates = vec![rate(1, 4.0), rate(4, 4.2)];
+        assert!(fill(&rates, FillPolicy::Error, Some(&BusinessCalendar::new())).is_ok());
+        match fill(&rates, FillPolicy::Error, None) {
+            Err(Error::Gap(gap)) => assert_eq!(gap, date(2)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Catalog module
+//!
+//! Static metadata about the currencies OpenExchangeRates reports, which `Currencies` only
+//! knows by name. ISO 4217 numeric codes and minor units are given where the currency has
+//! them; countries are ISO 3166-1 alpha-2 codes.
+use std::collections::BTreeMap;
+
+use Currencies;
+
+/// Whether a currency is in circulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CurrencyKind {
+    /// A current ISO 4217 currency.
+    Active,
+    /// A digital currency, precious metal or unofficial currency such as BTC or XAU.
+    Alternative,
+    /// A currency that has been replaced and only appears in historical data, such as EEK.
+    Obsolete,
+}
+
+/// Static metadata about a currency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CurrencyInfo {
+    pub code: &'static str,
+    /// ISO 4217 numeric code.
+    pub numeric: Option<u16>,
+    /// Number of digits after the decimal separator.
+    pub minor_units: Option<u8>,
+    pub symbol: &'static str,
+    pub countries: &'static [&'static str],
+    pub kind: CurrencyKind,
+}
+
+/// A currency returned by `Client::currencies`, along with its metadata if it is known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Currency {
+    pub code: String,
+    pub name: String,
+    pub info: Option<&'static CurrencyInfo>,
+}
+
+impl Currency {
+    /// The kind of the currency, or `None` if it is not in the catalog.
+    pub fn kind(&self) -> Option<CurrencyKind> {
+        self.info.map(|info| info.kind)
+    }
+
+    /// Whether the currency is a current ISO 4217 currency.
+    pub fn is_active(&self) -> bool {
+        self.kind() == Some(CurrencyKind::Active)
+    }
+}
+
+/// Look up the metadata of `code`.
+pub fn lookup(code: &str) -> Option<&'static CurrencyInfo> {
+    CATALOG.binary_search_by(|info| info.code.cmp(code)).ok().map(|index| &CATALOG[index])
+}
+
+/// Every currency in the catalog, sorted by code.
+pub fn all() -> &'static [CurrencyInfo] {
+    CATALOG
+}
+
+/// Attach catalog metadata to a `Currencies` response.
+pub fn merge(currencies: &Currencies) -> BTreeMap<String, Currency> {
+    currencies.iter()
+        .map(|(code, name)| {
+            (code.clone(),
+             Currency {
+                code: code.clone(),
+                name: name.clone(),
+                info: lookup(code),
+            })
+        })
+        .collect()
+}
+
+macro_rules! currency {
+    ($code:expr, $numeric:expr, $minor_units:expr, $symbol:expr, [$($country:expr),*],
+     $kind:ident) => {
+        CurrencyInfo {
+            code: $code,
+            numeric: $numeric,
+            minor_units: $minor_units,
+            symbol: $symbol,
+            countries: &[$($country),*],
+            kind: CurrencyKind::$kind,
+        }
+    }
+}
+
+static CATALOG: &'static [CurrencyInfo] = &[
+    currency!("AED", Some(784), Some(2), "د.إ", ["AE"], Active),
+    currency!("AFN", Some(971), Some(2), "؋", ["AF"], Active),
+    currency!("ALL", Some(8), Some(2), "L", ["AL"], Active),
+    currency!("AMD", Some(51), Some(2), "֏", ["AM"], Active),
+    currency!("ANG", Some(532), Some(2), "ƒ", ["CW", "SX"], Active),
+    currency!("AOA", Some(973), Some(2), "Kz", ["AO"], Active),
+    currency!("ARS", Some(32), Some(2), "$", ["AR"], Active),
+    currency!("AUD", Some(36), Some(2), "$",
+              ["AU", "CC", "CX", "HM", "KI", "NF", "NR", "TV"],
+              Active),
+    currency!("AWG", Some(533), Some(2), "ƒ", ["AW"], Active),
+    currency!("AZN", Some(944), Some(2), "₼", ["AZ"], Active),
+    currency!("BAM", Some(977), Some(2), "KM", ["BA"], Active),
+    currency!("BBD", Some(52), Some(2), "$", ["BB"], Active),
+    currency!("BDT", Some(50), Some(2), "৳", ["BD"], Active),
+    currency!("BGN", Some(975), Some(2), "лв", ["BG"], Obsolete),
+    currency!("BHD", Some(48), Some(3), ".د.ب", ["BH"], Active),
+    currency!("BIF", Some(108), Some(0), "FBu", ["BI"], Active),
+    currency!("BMD", Some(60), Some(2), "$", ["BM"], Active),
+    currency!("BND", Some(96), Some(2), "$", ["BN"], Active),
+    currency!("BOB", Some(68), Some(2), "Bs.", ["BO"], Active),
+    currency!("BRL", Some(986), Some(2), "R$", ["BR"], Active),
+    currency!("BSD", Some(44), Some(2), "$", ["BS"], Active),
+    currency!("BTC", None, Some(8), "₿", [], Alternative),
+    currency!("BTN", Some(64), Some(2), "Nu.", ["BT"], Active),
+    currency!("BWP", Some(72), Some(2), "P", ["BW"], Active),
+    currency!("BYN", Some(933), Some(2), "Br", ["BY"], Active),
+    currency!("BYR", Some(974), Some(0), "Br", ["BY"], Obsolete),
+    currency!("BZD", Some(84), Some(2), "$", ["BZ"], Active),
+    currency!("CAD", Some(124), Some(2), "$", ["CA"], Active),
+    currency!("CDF", Some(976), Some(2), "FC", ["CD"], Active),
+    currency!("CHF", Some(756), Some(2), "Fr", ["CH", "LI"], Active),
+    currency!("CLF", Some(990), Some(4), "UF", ["CL"], Active),
+    currency!("CLP", Some(152), Some(0), "$", ["CL"], Active),
+    currency!("CNH", None, Some(2), "¥", [], Alternative),
+    currency!("CNY", Some(156), Some(2), "¥", ["CN"], Active),
+    currency!("COP", Some(170), Some(2), "$", ["CO"], Active),
+    currency!("CRC", Some(188), Some(2), "₡", ["CR"], Active),
+    currency!("CUC", Some(931), Some(2), "$", ["CU"], Obsolete),
+    currency!("CUP", Some(192), Some(2), "$", ["CU"], Active),
+    currency!("CVE", Some(132), Some(2), "$", ["CV"], Active),
+    currency!("CZK", Some(203), Some(2), "Kč", ["CZ"], Active),
+    currency!("DJF", Some(262), Some(0), "Fdj", ["DJ"], Active),
+    currency!("DKK", Some(208), Some(2), "kr", ["DK", "FO", "GL"], Active),
+    currency!("DOP", Some(214), Some(2), "$", ["DO"], Active),
+    currency!("DZD", Some(12), Some(2), "د.ج", ["DZ"], Active),
+    currency!("EEK", Some(233), Some(2), "kr", ["EE"], Obsolete),
+    currency!("EGP", Some(818), Some(2), "£", ["EG"], Active),
+    currency!("ERN", Some(232), Some(2), "Nfk", ["ER"], Active),
+    currency!("ETB", Some(230), Some(2), "Br", ["ET"], Active),
+    currency!("EUR", Some(978), Some(2), "€",
+              ["AD", "AT", "BE", "BG", "CY", "DE", "EE", "ES", "FI", "FR", "GR", "HR",
+               "IE", "IT", "LT", "LU", "LV", "MC", "ME", "MT", "NL", "PT", "SI", "SK",
+               "SM", "VA"],
+              Active),
+    currency!("FJD", Some(242), Some(2), "$", ["FJ"], Active),
+    currency!("FKP", Some(238), Some(2), "£", ["FK"], Active),
+    currency!("GBP", Some(826), Some(2), "£", ["GB", "GG", "IM", "JE"], Active),
+    currency!("GEL", Some(981), Some(2), "₾", ["GE"], Active),
+    currency!("GGP", None, Some(2), "£", ["GG"], Alternative),
+    currency!("GHS", Some(936), Some(2), "₵", ["GH"], Active),
+    currency!("GIP", Some(292), Some(2), "£", ["GI"], Active),
+    currency!("GMD", Some(270), Some(2), "D", ["GM"], Active),
+    currency!("GNF", Some(324), Some(0), "FG", ["GN"], Active),
+    currency!("GTQ", Some(320), Some(2), "Q", ["GT"], Active),
+    currency!("GYD", Some(328), Some(2), "$", ["GY"], Active),
+    currency!("HKD", Some(344), Some(2), "$", ["HK"], Active),
+    currency!("HNL", Some(340), Some(2), "L", ["HN"], Active),
+    currency!("HRK", Some(191), Some(2), "kn", ["HR"], Obsolete),
+    currency!("HTG", Some(332), Some(2), "G", ["HT"], Active),
+    currency!("HUF", Some(348), Some(2), "Ft", ["HU"], Active),
+    currency!("IDR", Some(360), Some(2), "Rp", ["ID"], Active),
+    currency!("ILS", Some(376), Some(2), "₪", ["IL", "PS"], Active),
+    currency!("IMP", None, Some(2), "£", ["IM"], Alternative),
+    currency!("INR", Some(356), Some(2), "₹", ["IN", "BT"], Active),
+    currency!("IQD", Some(368), Some(3), "ع.د", ["IQ"], Active),
+    currency!("IRR", Some(364), Some(2), "﷼", ["IR"], Active),
+    currency!("ISK", Some(352), Some(0), "kr", ["IS"], Active),
+    currency!("JEP", None, Some(2), "£", ["JE"], Alternative),
+    currency!("JMD", Some(388), Some(2), "$", ["JM"], Active),
+    currency!("JOD", Some(400), Some(3), "د.ا", ["JO"], Active),
+    currency!("JPY", Some(392), Some(0), "¥", ["JP"], Active),
+    currency!("KES", Some(404), Some(2), "KSh", ["KE"], Active),
+    currency!("KGS", Some(417), Some(2), "с", ["KG"], Active),
+    currency!("KHR", Some(116), Some(2), "៛", ["KH"], Active),
+    currency!("KMF", Some(174), Some(0), "CF", ["KM"], Active),
+    currency!("KPW", Some(408), Some(2), "₩", ["KP"], Active),
+    currency!("KRW", Some(410), Some(0), "₩", ["KR"], Active),
+    currency!("KWD", Some(414), Some(3), "د.ك", ["KW"], Active),
+    currency!("KYD", Some(136), Some(2), "$", ["KY"], Active),
+    currency!("KZT", Some(398), Some(2), "₸", ["KZ"], Active),
+    currency!("LAK", Some(418), Some(2), "₭", ["LA"], Active),
+    currency!("LBP", Some(422), Some(2), "ل.ل", ["LB"], Active),
+    currency!("LKR", Some(144), Some(2), "Rs", ["LK"], Active),
+    currency!("LRD", Some(430), Some(2), "$", ["LR"], Active),
+    currency!("LSL", Some(426), Some(2), "L", ["LS"], Active),
+    currency!("LTL", Some(440), Some(2), "Lt", ["LT"], Obsolete),
+    currency!("LVL", Some(428), Some(2), "Ls", ["LV"], Obsolete),
+    currency!("LYD", Some(434), Some(3), "ل.د", ["LY"], Active),
+    currency!("MAD", Some(504), Some(2), "د.م.", ["MA", "EH"], Active),
+    currency!("MDL", Some(498), Some(2), "L", ["MD"], Active),
+    currency!("MGA", Some(969), Some(2), "Ar", ["MG"], Active),
+    currency!("MKD", Some(807), Some(2), "ден", ["MK"], Active),
+    currency!("MMK", Some(104), Some(2), "K", ["MM"], Active),
+    currency!("MNT", Some(496), Some(2), "₮", ["MN"], Active),
+    currency!("MOP", Some(446), Some(2), "MOP$", ["MO"], Active),
+    currency!("MRO", Some(478), Some(2), "UM", ["MR"], Obsolete),
+    currency!("MRU", Some(929), Some(2), "UM", ["MR"], Active),
+    currency!("MTL", Some(470), Some(2), "₤", ["MT"], Obsolete),
+    currency!("MUR", Some(480), Some(2), "₨", ["MU"], Active),
+    currency!("MVR", Some(462), Some(2), "Rf", ["MV"], Active),
+    currency!("MWK", Some(454), Some(2), "MK", ["MW"], Active),
+    currency!("MXN", Some(484), Some(2), "$", ["MX"], Active),
+    currency!("MYR", Some(458), Some(2), "RM", ["MY"], Active),
+    currency!("MZN", Some(943), Some(2), "MT", ["MZ"], Active),
+    currency!("NAD", Some(516), Some(2), "$", ["NA"], Active),
+    currency!("NGN", Some(566), Some(2), "₦", ["NG"], Active),
+    currency!("NIO", Some(558), Some(2), "C$", ["NI"], Active),
+    currency!("NOK", Some(578), Some(2), "kr", ["NO", "SJ", "BV"], Active),
+    currency!("NPR", Some(524), Some(2), "₨", ["NP"], Active),
+    currency!("NZD", Some(554), Some(2), "$", ["NZ", "CK", "NU", "PN", "TK"], Active),
+    currency!("OMR", Some(512), Some(3), "ر.ع.", ["OM"], Active),
+    currency!("PAB", Some(590), Some(2), "B/.", ["PA"], Active),
+    currency!("PEN", Some(604), Some(2), "S/", ["PE"], Active),
+    currency!("PGK", Some(598), Some(2), "K", ["PG"], Active),
+    currency!("PHP", Some(608), Some(2), "₱", ["PH"], Active),
+    currency!("PKR", Some(586), Some(2), "₨", ["PK"], Active),
+    currency!("PLN", Some(985), Some(2), "zł", ["PL"], Active),
+    currency!("PYG", Some(600), Some(0), "₲", ["PY"], Active),
+    currency!("QAR", Some(634), Some(2), "ر.ق", ["QA"], Active),
+    currency!("RON", Some(946), Some(2), "lei", ["RO"], Active),
+    currency!("RSD", Some(941), Some(2), "дин.", ["RS"], Active),
+    currency!("RUB", Some(643), Some(2), "₽", ["RU"], Active),
+    currency!("RWF", Some(646), Some(0), "FRw", ["RW"], Active),
+    currency!("SAR", Some(682), Some(2), "ر.س", ["SA"], Active),
+    currency!("SBD", Some(90), Some(2), "$", ["SB"], Active),
+    currency!("SCR", Some(690), Some(2), "₨", ["SC"], Active),
+    currency!("SDG", Some(938), Some(2), "ج.س.", ["SD"], Active),
+    currency!("SEK", Some(752), Some(2), "kr", ["SE"], Active),
+    currency!("SGD", Some(702), Some(2), "$", ["SG"], Active),
+    currency!("SHP", Some(654), Some(2), "£", ["SH"], Active),
+    currency!("SLE", Some(925), Some(2), "Le", ["SL"], Active),
+    currency!("SLL", Some(694), Some(2), "Le", ["SL"], Obsolete),
+    currency!("SOS", Some(706), Some(2), "Sh", ["SO"], Active),
+    currency!("SRD", Some(968), Some(2), "$", ["SR"], Active),
+    currency!("SSP", Some(728), Some(2), "£", ["SS"], Active),
+    currency!("STD", Some(678), Some(2), "Db", ["ST"], Obsolete),
+    currency!("STN", Some(930), Some(2), "Db", ["ST"], Active),
+    currency!("SVC", Some(222), Some(2), "₡", ["SV"], Active),
+    currency!("SYP", Some(760), Some(2), "£", ["SY"], Active),
+    currency!("SZL", Some(748), Some(2), "L", ["SZ"], Active),
+    currency!("THB", Some(764), Some(2), "฿", ["TH"], Active),
+    currency!("TJS", Some(972), Some(2), "SM", ["TJ"], Active),
+    currency!("TMT", Some(934), Some(2), "m", ["TM"], Active),
+    currency!("TND", Some(788), Some(3), "د.ت", ["TN"], Active),
+    currency!("TOP", Some(776), Some(2), "T$", ["TO"], Active),
+    currency!("TRY", Some(949), Some(2), "₺", ["TR"], Active),
+    currency!("TTD", Some(780), Some(2), "$", ["TT"], Active),
+    currency!("TWD", Some(901), Some(2), "$", ["TW"], Active),
+    currency!("TZS", Some(834), Some(2), "Sh", ["TZ"], Active),
+    currency!("UAH", Some(980), Some(2), "₴", ["UA"], Active),
+    currency!("UGX", Some(800), Some(0), "USh", ["UG"], Active),
+    currency!("USD", Some(840), Some(2), "$",
+              ["US", "AS", "BQ", "EC", "FM", "GU", "IO", "MH", "MP", "PR", "PW", "TC",
+               "TL", "UM", "VG", "VI"],
+              Active),
+    currency!("UYU", Some(858), Some(2), "$", ["UY"], Active),
+    currency!("UZS", Some(860), Some(2), "soʻm", ["UZ"], Active),
+    currency!("VEF", Some(937), Some(2), "Bs", ["VE"], Obsolete),
+    currency!("VES", Some(928), Some(2), "Bs.S", ["VE"], Active),
+    currency!("VND", Some(704), Some(0), "₫", ["VN"], Active),
+    currency!("VUV", Some(548), Some(0), "VT", ["VU"], Active),
+    currency!("WST", Some(882), Some(2), "T", ["WS"], Active),
+    currency!("XAF", Some(950), Some(0), "FCFA", ["CM", "CF", "TD", "CG", "GQ", "GA"], Active),
+    currency!("XAG", Some(961), None, "", [], Alternative),
+    currency!("XAU", Some(959), None, "", [], Alternative),
+    currency!("XCD", Some(951), Some(2), "$",
+              ["AG", "AI", "DM", "GD", "KN", "LC", "MS", "VC"],
+              Active),
+    currency!("XDR", Some(960), None, "SDR", [], Active),
+    currency!("XOF", Some(952), Some(0), "CFA",
+              ["BJ", "BF", "CI", "GW", "ML", "NE", "SN", "TG"],
+              Active),
+    currency!("XPD", Some(964), None, "", [], Alternative),
+    currency!("XPF", Some(953), Some(0), "₣", ["NC", "PF", "WF"], Active),
+    currency!("XPT", Some(962), None, "", [], Alternative),
+    currency!("YER", Some(886), Some(2), "﷼", ["YE"], Active),
+    currency!("ZAR", Some(710), Some(2), "R", ["ZA", "LS", "NA"], Active),
+    currency!("ZMK", Some(894), Some(2), "ZK", ["ZM"], Obsolete),
+    currency!("ZMW", Some(967), Some(2), "ZK", ["ZM"], Active),
+    currency!("ZWG", Some(924), Some(2), "ZiG", ["ZW"], Active),
+    currency!("ZWL", Some(932), Some(2), "$", ["ZW"], Obsolete),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalog_is_sorted() {
+        for pair in CATALOG.windows(2) {
+            assert!(pair[0].code < pair[1].code,
+                    "{} must come before {}",
+                    pair[0].code,
+                    pair[1].code);
+        }
+    }
+
+    #[test]
+    fn lookup_active() {
+        let myr = lookup("MYR").unwrap();
+        assert_eq!(myr.numeric, Some(458));
+        assert_eq!(myr.minor_units, Some(2));
+        assert_eq!(myr.countries, &["MY"]);
+        assert_eq!(myr.kind, CurrencyKind::Active);
+        assert_eq!(lookup("ZWG").unwrap().numeric, Some(924));
+        assert!(lookup("EUR").unwrap().countries.contains(&"BG"));
+    }
+
+    #[test]
+    fn lookup_alternative_and_obsolete() {
+        assert_eq!(lookup("BTC").unwrap().kind, CurrencyKind::Alternative);
+        assert_eq!(lookup("XAU").unwrap().minor_units, None);
+        for code in &["BGN", "CUC", "EEK", "LTL", "MTL", "SLL", "ZMK", "ZWL"] {
+            assert_eq!(lookup(code).unwrap().kind, CurrencyKind::Obsolete);
+        }
+    }
+
+    #[test]
+    fn lookup_unknown() {
+        assert_eq!(lookup("XXX"), None);
+    }
+
+    #[test]
+    fn merge_names() {
+        let mut currencies = Currencies::new();
+        currencies.insert("MYR".to_owned(), "Malaysian Ringgit".to_owned());
+        currencies.insert("XYZ".to_owned(), "Unknown".to_owned());
+
+        let merged = merge(&currencies);
+        assert_eq!(merged["MYR"].name, "Malaysian Ringgit");
+        assert!(merged["MYR"].is_active());
+        assert_eq!(merged["XYZ"].info, None);
+        assert_eq!(merged["XYZ"].kind(), None);
+        assert!(!merged["XYZ"].is_active());
+    }
+}
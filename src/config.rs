@@ -0,0 +1,348 @@
+//! Config module
+//!
+//! A `Config` can be read from a JSON file:
+//!
+//! ```json
+//! {
+//!   "app_id": "1234",
+//!   "base_url": "https://openexchangerates.org/api",
+//!   "timeout": 10,
+//!   "base": "USD",
+//!   "max_age": 7200,
+//!   "cache": { "ttl": 3600, "capacity": 64 }
+//! }
+//! ```
+//!
+//! or from the `OXR_*` environment variables. Only `app_id` is required. Other formats such as
+//! TOML are not supported, to keep the dependencies down.
+//!
+//! `Client::from_config` applies every setting but `cache`, which configures a
+//! `provider::CachingProvider` through `CachingProvider::from_config`.
+use std::env;
+use std::fs::File;
+use std::i64;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono;
+use serde_json;
+
+use error::Error;
+use {CacheConfig, Client, Config};
+
+/// Environment variable holding the App ID.
+pub const APP_ID_VAR: &'static str = "OXR_APP_ID";
+/// Environment variable holding the location of the API.
+pub const BASE_URL_VAR: &'static str = "OXR_BASE_URL";
+/// Environment variable holding the timeout in seconds.
+pub const TIMEOUT_VAR: &'static str = "OXR_TIMEOUT";
+/// Environment variable holding the default base currency.
+pub const BASE_VAR: &'static str = "OXR_BASE";
+/// Environment variable holding the maximum age of the latest rates in seconds.
+pub const MAX_AGE_VAR: &'static str = "OXR_MAX_AGE";
+/// Environment variable holding the cache time-to-live in seconds.
+pub const CACHE_TTL_VAR: &'static str = "OXR_CACHE_TTL";
+/// Environment variable holding the maximum number of cached responses.
+pub const CACHE_CAPACITY_VAR: &'static str = "OXR_CACHE_CAPACITY";
+/// Environment variable pointing to a config file, which takes precedence over the other
+/// variables.
+pub const CONFIG_VAR: &'static str = "OXR_CONFIG";
+
+/// Largest number of seconds accepted for `timeout` and `max_age`, the most a
+/// `chrono::Duration` can hold.
+const MAX_SECONDS: u64 = (i64::MAX / 1000) as u64;
+
+impl Config {
+    /// Read the config from a JSON file.
+    pub fn from_file<P>(path: P) -> Result<Config, Error>
+        where P: AsRef<Path>
+    {
+        let mut file = try!(File::open(path));
+        let mut contents = String::new();
+        try!(file.read_to_string(&mut contents));
+        Config::parse(&contents)
+    }
+
+    /// Parse the config from a JSON string.
+    pub fn parse(s: &str) -> Result<Config, Error> {
+        let config: Config = try!(serde_json::from_str(s));
+        config.validate()
+    }
+
+    /// Read the config from the `OXR_*` environment variables.
+    ///
+    /// If `OXR_CONFIG` is set, the file it points to is read instead.
+    pub fn from_env() -> Result<Config, Error> {
+        if let Some(path) = env::var_os(CONFIG_VAR) {
+            return Config::from_file(path);
+        }
+        Config::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars<F>(var: F) -> Result<Config, Error>
+        where F: Fn(&str) -> Option<String>
+    {
+        let app_id = match var(APP_ID_VAR) {
+            Some(app_id) => app_id,
+            None => {
+                return Err(Error::Config(format!("environment variable `{}` is not set",
+                                                 APP_ID_VAR)))
+            }
+        };
+
+        let cache = match (var(CACHE_TTL_VAR), var(CACHE_CAPACITY_VAR)) {
+            (Some(ttl), capacity) => {
+                Some(CacheConfig {
+                    ttl: try!(parse_var(CACHE_TTL_VAR, &ttl)),
+                    capacity: match capacity {
+                        Some(capacity) => Some(try!(parse_var(CACHE_CAPACITY_VAR, &capacity))),
+                        None => None,
+                    },
+                })
+            }
+            (None, Some(_)) => {
+                return Err(Error::Config(format!("environment variable `{}` requires `{}`",
+                                                 CACHE_CAPACITY_VAR,
+                                                 CACHE_TTL_VAR)))
+            }
+            (None, None) => None,
+        };
+
+        let config = Config {
+            app_id: app_id,
+            base_url: var(BASE_URL_VAR),
+            timeout: match var(TIMEOUT_VAR) {
+                Some(timeout) => Some(try!(parse_var(TIMEOUT_VAR, &timeout))),
+                None => None,
+            },
+            base: var(BASE_VAR),
+            max_age: match var(MAX_AGE_VAR) {
+                Some(max_age) => Some(try!(parse_var(MAX_AGE_VAR, &max_age))),
+                None => None,
+            },
+            cache: cache,
+        };
+        config.validate()
+    }
+
+    fn validate(self) -> Result<Config, Error> {
+        if self.app_id.trim().is_empty() {
+            return Err(Error::Config("`app_id` must not be empty".to_owned()));
+        }
+        if let Some(ref base_url) = self.base_url {
+            if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+                return Err(Error::Config(format!("`base_url` must be an http(s) URL, got `{}`",
+                                                 base_url)));
+            }
+        }
+        if let Some(timeout) = self.timeout {
+            if timeout == 0 {
+                return Err(Error::Config("`timeout` must be greater than zero".to_owned()));
+            }
+            try!(seconds("timeout", timeout));
+        }
+        if let Some(max_age) = self.max_age {
+            try!(seconds("max_age", max_age));
+        }
+        if let Some(ref base) = self.base {
+            if base.len() != 3 || !base.bytes().all(|b| b >= b'A' && b <= b'Z') {
+                return Err(Error::Config(format!("`base` must be a three-letter currency \
+                                                  code, got `{}`",
+                                                 base)));
+            }
+        }
+        if let Some(ref cache) = self.cache {
+            if cache.capacity == Some(0) {
+                return Err(Error::Config("`cache.capacity` must be greater than zero"
+                    .to_owned()));
+            }
+        }
+        Ok(self)
+    }
+}
+
+fn parse_var<T>(name: &str, value: &str) -> Result<T, Error>
+    where T: ::std::str::FromStr
+{
+    value.trim()
+        .parse()
+        .map_err(|_| Error::Config(format!("environment variable `{}` must be a number, got `{}`",
+                                           name,
+                                           value)))
+}
+
+/// `value` as a signed number of seconds, or an error if it is larger than `MAX_SECONDS`.
+fn seconds(name: &str, value: u64) -> Result<i64, Error> {
+    if value > MAX_SECONDS {
+        return Err(Error::Config(format!("`{}` must be at most {} seconds, got {}",
+                                         name,
+                                         MAX_SECONDS,
+                                         value)));
+    }
+    Ok(value as i64)
+}
+
+impl<'a> Client<'a> {
+    /// Create a client from the `OXR_*` environment variables, see `Config::from_env`.
+    pub fn from_env() -> Result<Client<'a>, Error> {
+        let config = try!(Config::from_env());
+        Client::from_config(&config)
+    }
+
+    /// Create a client using the settings in `config`.
+    ///
+    /// Fails with `Error::Config` if `timeout` or `max_age` is out of range, which can only
+    /// happen when `config` was not built by `Config::parse` or `Config::from_env`.
+    /// `config.cache` is not used here, see `provider::CachingProvider::from_config`.
+    pub fn from_config(config: &Config) -> Result<Client<'a>, Error> {
+        let mut client = Client::new(config.app_id.clone());
+        if let Some(ref base_url) = config.base_url {
+            client = client.base_url(base_url.clone());
+        }
+        if let Some(ref base) = config.base {
+            client = client.base(base.clone());
+        }
+        if let Some(timeout) = config.timeout {
+            try!(seconds("timeout", timeout));
+            client = client.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(max_age) = config.max_age {
+            client = client.max_age(chrono::Duration::seconds(try!(seconds("max_age", max_age))));
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use error::Error;
+    use {CacheConfig, Client, Config};
+
+    #[test]
+    fn parse_full() {
+        let config = Config::parse(r#"{
+            "app_id": "1234",
+            "base_url": "http://localhost:8080/api",
+            "timeout": 10,
+            "base": "EUR",
+            "max_age": 7200,
+            "cache": { "ttl": 3600, "capacity": 64 }
+        }"#)
+            .unwrap();
+        assert_eq!(config,
+                   Config {
+                       app_id: "1234".to_owned(),
+                       base_url: Some("http://localhost:8080/api".to_owned()),
+                       timeout: Some(10),
+                       base: Some("EUR".to_owned()),
+                       max_age: Some(7200),
+                       cache: Some(CacheConfig {
+                           ttl: 3600,
+                           capacity: Some(64),
+                       }),
+                   });
+    }
+
+    #[test]
+    fn parse_minimal() {
+        let config = Config::parse(r#"{ "app_id": "1234" }"#).unwrap();
+        assert_eq!(config.app_id, "1234");
+        assert_eq!(config.base_url, None);
+        assert_eq!(config.cache, None);
+    }
+
+    #[test]
+    fn parse_missing_app_id() {
+        match Config::parse(r#"{ "base": "EUR" }"#) {
+            Err(Error::SerdeJson(err)) => assert!(err.to_string().contains("app_id")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_unknown_field() {
+        assert!(Config::parse(r#"{ "app_id": "1234", "app-id": "5678" }"#).is_err());
+    }
+
+    #[test]
+    fn parse_invalid_base() {
+        match Config::parse(r#"{ "app_id": "1234", "base": "euro" }"#) {
+            Err(Error::Config(msg)) => assert!(msg.contains("euro")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_out_of_range_durations() {
+        for field in &["timeout", "max_age"] {
+            let json = format!(r#"{{ "app_id": "1234", "{}": 18446744073709551615 }}"#, field);
+            match Config::parse(&json) {
+                Err(Error::Config(msg)) => assert!(msg.contains(field)),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn from_config_out_of_range_max_age() {
+        let mut config = Config::parse(r#"{ "app_id": "1234" }"#).unwrap();
+        config.max_age = Some(::std::i64::MAX as u64);
+        match Client::from_config(&config) {
+            Err(Error::Config(msg)) => assert!(msg.contains("max_age")),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn vars_full() {
+        let mut vars = BTreeMap::new();
+        vars.insert("OXR_APP_ID", "1234");
+        vars.insert("OXR_TIMEOUT", "5");
+        vars.insert("OXR_BASE", "EUR");
+        vars.insert("OXR_CACHE_TTL", "60");
+        let config = Config::from_vars(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(config.app_id, "1234");
+        assert_eq!(config.timeout, Some(5));
+        assert_eq!(config.base, Some("EUR".to_owned()));
+        assert_eq!(config.cache,
+                   Some(CacheConfig {
+                       ttl: 60,
+                       capacity: None,
+                   }));
+    }
+
+    #[test]
+    fn vars_capacity_without_ttl() {
+        let mut vars = BTreeMap::new();
+        vars.insert("OXR_APP_ID", "1234");
+        vars.insert("OXR_CACHE_CAPACITY", "64");
+        match Config::from_vars(|name| vars.get(name).map(|v| v.to_string())) {
+            Err(Error::Config(msg)) => {
+                assert!(msg.contains("OXR_CACHE_CAPACITY") && msg.contains("OXR_CACHE_TTL"))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn vars_missing_app_id() {
+        match Config::from_vars(|_| None) {
+            Err(Error::Config(msg)) => assert!(msg.contains("OXR_APP_ID")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn vars_malformed_timeout() {
+        let mut vars = BTreeMap::new();
+        vars.insert("OXR_APP_ID", "1234");
+        vars.insert("OXR_TIMEOUT", "ten");
+        match Config::from_vars(|name| vars.get(name).map(|v| v.to_string())) {
+            Err(Error::Config(msg)) => assert!(msg.contains("OXR_TIMEOUT") && msg.contains("ten")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Conversion module
+use std::collections::BTreeMap;
+
+use ExchangeRate;
+use error::Error;
+
+impl ExchangeRate {
+    /// The rate of `currency` relative to the base currency.
+    ///
+    /// The base currency itself has a rate of 1 even if it is absent from `rates`.
+    pub fn rate(&self, currency: &str) -> Option<f64> {
+        match self.rates.get(currency) {
+            Some(rate) => Some(*rate),
+            None if currency == self.base => Some(1.0),
+            None => None,
+        }
+    }
+
+    /// The number of `to` one unit of `from` buys.
+    ///
+    /// Returns `None` if either currency is unknown.
+    pub fn cross_rate(&self, from: &str, to: &str) -> Option<f64> {
+        match (self.rate(from), self.rate(to)) {
+            (Some(from), Some(to)) if from != 0.0 => Some(to / from),
+            _ => None,
+        }
+    }
+
+    /// Convert `amount` of `from` into `to`.
+    ///
+    /// Returns `None` if either currency is unknown.
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Option<f64> {
+        self.cross_rate(from, to).map(|rate| amount * rate)
+    }
+
+    /// Express the rates relative to `base` instead.
+    ///
+    /// The former base currency gets a rate, while `base` is only listed in `rates` if the former
+    /// base was, as the API does. Fails with `Error::MissingRate` if there is no rate for `base`.
+    pub fn rebase(&self, base: &str) -> Result<ExchangeRate, Error> {
+        let factor = match self.rate(base) {
+            Some(rate) if rate != 0.0 => rate,
+            _ => return Err(Error::MissingRate(base.to_owned())),
+        };
+
+        let mut rates: BTreeMap<String, f64> = self.rates
+            .iter()
+            .map(|(currency, rate)| (currency.clone(), rate / factor))
+            .collect();
+        rates.insert(self.base.clone(), 1.0 / factor);
+        if self.rates.contains_key(&self.base) {
+            rates.insert(base.to_owned(), 1.0);
+        } else {
+            rates.remove(base);
+        }
+
+        Ok(ExchangeRate {
+            disclaimer: self.disclaimer.clone(),
+            license: self.license.clone(),
+            timestamp: self.timestamp,
+            base: base.to_owned(),
+            rates: rates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ExchangeRate;
+
+    fn rate() -> ExchangeRate {
+        ExchangeRate::fixture(1459854002, "USD", &[("EUR", 0.8), ("MYR", 4.0)])
+    }
+
+    #[test]
+    fn convert_from_base() {
+        assert_eq!(rate().convert(10.0, "USD", "MYR"), Some(40.0));
+    }
+
+    #[test]
+    fn convert_to_base() {
+        assert_eq!(rate().convert(40.0, "MYR", "USD"), Some(10.0));
+    }
+
+    #[test]
+    fn convert_cross() {
+        assert_eq!(rate().convert(8.0, "EUR", "MYR"), Some(40.0));
+    }
+
+    #[test]
+    fn rebase() {
+        let rate = rate().rebase("EUR").unwrap();
+        assert_eq!(rate.base, "EUR");
+        assert_eq!(rate.rate("EUR"), Some(1.0));
+        assert_eq!(rate.rate("USD"), Some(1.25));
+        assert_eq!(rate.rate("MYR"), Some(5.0));
+    }
+
+    #[test]
+    fn rebase_lists_base_like_source() {
+        let rate = rate().rebase("EUR").unwrap();
+        assert!(!rate.rates.contains_key("EUR"));
+        assert_eq!(rate.rates["USD"], 1.25);
+
+        let mut listed = self::rate();
+        listed.rates.insert("USD".to_owned(), 1.0);
+        assert_eq!(listed.rebase("EUR").unwrap().rates["EUR"], 1.0);
+    }
+
+    #[test]
+    fn rebase_unknown() {
+        assert!(rate().rebase("XXX").is_err());
+    }
+
+    #[test]
+    fn convert_unknown() {
+        assert_eq!(rate().convert(1.0, "USD", "XXX"), None);
+    }
+}
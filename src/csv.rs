@@ -0,0 +1,347 @@
+//! CSV module
+//!
+//! Exchange rates can be written in two layouts:
+//!
+//! * wide, one row per snapshot and one column per currency:
+//!
+//!   ```text
+//!   date,timestamp,base,AED,AFN,...
+//!   2016-04-05,1459854002,USD,3.673125,68.589998,...
+//!   ```
+//!
+//! * long, one row per currency of every snapshot:
+//!
+//!   ```text
+//!   date,base,currency,rate
+//!   2016-04-05,USD,AED,3.673125
+//!   ```
+//!
+//! Both can be read back into `ExchangeRate`s, rejecting rates that are not finite numbers
+//! and, in the wide layout, dates other than the date of `timestamp`. The disclaimer and
+//! license are not stored, and since the long layout only keeps the date, snapshots read from
+//! it are timestamped at midnight UTC.
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, BufReader, Read, Write};
+
+use chrono::NaiveDate;
+
+use ExchangeRate;
+use error::Error;
+use timestamp;
+
+const WIDE_HEADER: [&'static str; 3] = ["date", "timestamp", "base"];
+const LONG_HEADER: [&'static str; 4] = ["date", "base", "currency", "rate"];
+
+/// Write `rates` in the wide layout.
+///
+/// The columns are the union of the currencies of every snapshot; a currency missing from a
+/// snapshot is left empty.
+pub fn write_wide<W>(mut writer: W, rates: &[ExchangeRate]) -> Result<(), Error>
+    where W: Write
+{
+    let currencies: BTreeSet<&str> = rates.iter()
+        .flat_map(|rate| rate.rates.keys().map(|currency| currency.as_ref()))
+        .collect();
+
+    let header: Vec<&str> = WIDE_HEADER.iter().cloned().chain(currencies.iter().cloned()).collect();
+    try!(writeln!(writer, "{}", header.join(",")));
+
+    for rate in rates {
+        let mut row = vec![rate.date().to_string(), rate.timestamp.to_string(), rate.base.clone()];
+        for currency in &currencies {
+            row.push(rate.rates.get(*currency).map_or_else(String::new, |v| v.to_string()));
+        }
+        try!(writeln!(writer, "{}", row.join(",")));
+    }
+    Ok(())
+}
+
+/// Write `rates` in the long layout.
+pub fn write_long<W>(mut writer: W, rates: &[ExchangeRate]) -> Result<(), Error>
+    where W: Write
+{
+    try!(writeln!(writer, "{}", LONG_HEADER.join(",")));
+    for rate in rates {
+        let date = rate.date();
+        for (currency, value) in &rate.rates {
+            try!(writeln!(writer, "{},{},{},{}", date, rate.base, currency, value));
+        }
+    }
+    Ok(())
+}
+
+/// Read snapshots written in the wide layout.
+pub fn read_wide<R>(reader: R) -> Result<Vec<ExchangeRate>, Error>
+    where R: Read
+{
+    let mut records = Records::new(reader);
+    let header = match try!(records.next_record()) {
+        Some((_, header)) => header,
+        None => return Ok(Vec::new()),
+    };
+    if header.len() < WIDE_HEADER.len() || header[..WIDE_HEADER.len()] != WIDE_HEADER[..] {
+        return Err(Error::Csv(format!("line 1: expected header to start with `{}`",
+                                      WIDE_HEADER.join(","))));
+    }
+
+    let mut rates = Vec::new();
+    while let Some((line, record)) = try!(records.next_record()) {
+        if record.len() != header.len() {
+            return Err(Error::Csv(format!("line {}: expected {} fields, got {}",
+                                          line,
+                                          header.len(),
+                                          record.len())));
+        }
+
+        let mut rate = ExchangeRate {
+            disclaimer: String::new(),
+            license: String::new(),
+            timestamp: try!(parse_field(line, "timestamp", &record[1]).and_then(|timestamp| {
+                timestamp::check(timestamp)
+                    .map_err(|_| Error::Csv(format!("line {}: timestamp out of range", line)))
+            })),
+            base: record[2].clone(),
+            rates: BTreeMap::new(),
+        };
+        if try!(parse_date(line, &record[0])) != rate.date() {
+            return Err(Error::Csv(format!("line {}: date `{}` is not the date of timestamp `{}`",
+                                          line,
+                                          record[0],
+                                          record[1])));
+        }
+        for (currency, value) in header.iter().zip(&record).skip(WIDE_HEADER.len()) {
+            if !value.is_empty() {
+                rate.rates.insert(currency.clone(), try!(parse_rate(line, currency, value)));
+            }
+        }
+        rates.push(rate);
+    }
+    Ok(rates)
+}
+
+/// Read snapshots written in the long layout.
+///
+/// Rows are grouped into one snapshot per date and base, in the order they first appear.
+pub fn read_long<R>(reader: R) -> Result<Vec<ExchangeRate>, Error>
+    where R: Read
+{
+    let mut records = Records::new(reader);
+    match try!(records.next_record()) {
+        Some((_, ref header)) if header[..] == LONG_HEADER[..] => {}
+        Some(_) => {
+            return Err(Error::Csv(format!("line 1: expected header `{}`", LONG_HEADER.join(","))))
+        }
+        None => return Ok(Vec::new()),
+    }
+
+    let mut rates: Vec<ExchangeRate> = Vec::new();
+    let mut indices = BTreeMap::new();
+    while let Some((line, record)) = try!(records.next_record()) {
+        if record.len() != LONG_HEADER.len() {
+            return Err(Error::Csv(format!("line {}: expected {} fields, got {}",
+                                          line,
+                                          LONG_HEADER.len(),
+                                          record.len())));
+        }
+
+        let date = try!(parse_date(line, &record[0]));
+        let timestamp = date.and_hms(0, 0, 0).timestamp();
+        let value = try!(parse_rate(line, &record[2], &record[3]));
+
+        let key = (timestamp, record[1].clone());
+        let position = indices.get(&key).cloned();
+        let index = match position {
+            Some(index) => index,
+            None => {
+                rates.push(ExchangeRate {
+                    disclaimer: String::new(),
+                    license: String::new(),
+                    timestamp: timestamp,
+                    base: record[1].clone(),
+                    rates: BTreeMap::new(),
+                });
+                indices.insert(key, rates.len() - 1);
+                rates.len() - 1
+            }
+        };
+        rates[index].rates.insert(record[2].clone(), value);
+    }
+    Ok(rates)
+}
+
+fn parse_date(line: usize, value: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| Error::Csv(format!("line {}: invalid date `{}`", line, value)))
+}
+
+/// Parse the rate of `currency`, which must be a finite number.
+fn parse_rate(line: usize, currency: &str, value: &str) -> Result<f64, Error> {
+    let rate: f64 = try!(parse_field(line, currency, value));
+    if !rate.is_finite() {
+        return Err(Error::Csv(format!("line {}: rate `{}` for `{}` is not finite",
+                                      line,
+                                      value,
+                                      currency)));
+    }
+    Ok(rate)
+}
+
+fn parse_field<T>(line: usize, name: &str, value: &str) -> Result<T, Error>
+    where T: ::std::str::FromStr
+{
+    value.parse()
+        .map_err(|_| Error::Csv(format!("line {}: invalid value `{}` for `{}`", line, value, name)))
+}
+
+/// Iterates over the non-empty records of a CSV file, along with their line number.
+struct Records<R> {
+    lines: ::std::io::Lines<BufReader<R>>,
+    line: usize,
+}
+
+impl<R> Records<R>
+    where R: Read
+{
+    fn new(reader: R) -> Records<R> {
+        Records {
+            lines: BufReader::new(reader).lines(),
+            line: 0,
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<(usize, Vec<String>)>, Error> {
+        while let Some(line) = self.lines.next() {
+            let line = try!(line);
+            self.line += 1;
+            let line = line.trim_right_matches('\r');
+            if !line.trim().is_empty() {
+                let record = try!(split_record(line)
+                    .map_err(|msg| Error::Csv(format!("line {}: {}", self.line, msg))));
+                return Ok(Some((self.line, record)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Split a line into fields, honouring double quoted fields as written by spreadsheets.
+fn split_record(line: &str) -> Result<Vec<String>, &'static str> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => fields.push(::std::mem::replace(&mut field, String::new())),
+            (c, _) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field");
+    }
+    fields.push(field);
+    Ok(fields.into_iter().map(|field| field.trim().to_owned()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use ExchangeRate;
+    use error::Error;
+
+    fn rate(timestamp: i64, rates: &[(&str, f64)]) -> ExchangeRate {
+        ExchangeRate::fixture(timestamp, "USD", rates)
+    }
+
+    #[test]
+    fn wide_round_trip() {
+        let rates = vec![rate(1459854002, &[("EUR", 0.880403), ("MYR", 3.917198)]),
+                         rate(1361055600, &[("MYR", 3.094163), ("BTC", 0.036871)])];
+
+        let mut out = Vec::new();
+        super::write_wide(&mut out, &rates).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(),
+                   "date,timestamp,base,BTC,EUR,MYR\n\
+                    2016-04-05,1459854002,USD,,0.880403,3.917198\n\
+                    2013-02-16,1361055600,USD,0.036871,,3.094163\n");
+
+        assert_eq!(super::read_wide(&out[..]).unwrap(), rates);
+    }
+
+    #[test]
+    fn long_round_trip() {
+        let rates = vec![rate(1459814400, &[("EUR", 0.880403), ("MYR", 3.917198)]),
+                         rate(1459900800, &[("MYR", 3.9)])];
+
+        let mut out = Vec::new();
+        super::write_long(&mut out, &rates).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(),
+                   "date,base,currency,rate\n\
+                    2016-04-05,USD,EUR,0.880403\n\
+                    2016-04-05,USD,MYR,3.917198\n\
+                    2016-04-06,USD,MYR,3.9\n");
+
+        assert_eq!(super::read_long(&out[..]).unwrap(), rates);
+    }
+
+    #[test]
+    fn read_quoted_and_crlf() {
+        let csv = "\"date\",\"base\",\"currency\",\"rate\"\r\n2016-04-05,USD,MYR,\"3.9\"\r\n";
+        let rates = super::read_long(csv.as_bytes()).unwrap();
+        assert_eq!(rates, vec![rate(1459814400, &[("MYR", 3.9)])]);
+    }
+
+    #[test]
+    fn read_invalid_rate() {
+        let csv = "date,base,currency,rate\n2016-04-05,USD,MYR,abc\n";
+        match super::read_long(csv.as_bytes()) {
+            Err(Error::Csv(msg)) => assert!(msg.starts_with("line 2") && msg.contains("abc")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_non_finite_rate() {
+        for value in &["NaN", "inf", "-inf"] {
+            let csv = format!("date,base,currency,rate\n2016-04-05,USD,MYR,{}\n", value);
+            match super::read_long(csv.as_bytes()) {
+                Err(Error::Csv(msg)) => assert!(msg.contains("not finite")),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        let csv = "date,timestamp,base,MYR\n2016-04-05,1459854002,USD,NaN\n";
+        assert!(super::read_wide(csv.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn read_date_of_other_timestamp() {
+        let csv = "date,timestamp,base,MYR\n2016-04-06,1459854002,USD,3.9\n";
+        match super::read_wide(csv.as_bytes()) {
+            Err(Error::Csv(msg)) => {
+                assert_eq!(msg,
+                           "line 2: date `2016-04-06` is not the date of timestamp `1459854002`")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_timestamp_out_of_range() {
+        let csv = "date,timestamp,base,MYR\n2016-04-05,9223372036854775807,USD,3.9\n";
+        match super::read_wide(csv.as_bytes()) {
+            Err(Error::Csv(msg)) => assert_eq!(msg, "line 2: timestamp out of range"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_wrong_header() {
+        assert!(super::read_wide("currency,rate\nMYR,3.9\n".as_bytes()).is_err());
+    }
+}
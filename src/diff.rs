@@ -0,0 +1,176 @@
+//! Diff module
+use std::collections::BTreeMap;
+
+use ExchangeRate;
+use error::Error;
+
+/// The change of one currency between two snapshots.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Change {
+    pub old: f64,
+    pub new: f64,
+    /// `new - old`.
+    pub absolute: f64,
+    /// `(new - old) / old`: zero if both are zero, infinite if only `old` is.
+    pub relative: f64,
+}
+
+impl Change {
+    fn new(old: f64, new: f64) -> Change {
+        Change {
+            old: old,
+            new: new,
+            absolute: new - old,
+            relative: if new == old {
+                0.0
+            } else if old == 0.0 {
+                ::std::f64::INFINITY
+            } else {
+                (new - old) / old
+            },
+        }
+    }
+
+    /// The relative change in percent.
+    pub fn percent(&self) -> f64 {
+        self.relative * 100.0
+    }
+}
+
+/// The differences between two snapshots, see `ExchangeRate::diff`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateDiff {
+    /// The base currency both snapshots are compared in.
+    pub base: String,
+    /// Whether the newer snapshot had another base and was rebased onto `base`.
+    pub rebased: bool,
+    pub old_timestamp: i64,
+    pub new_timestamp: i64,
+    /// Currencies present in both snapshots, including those that did not move.
+    pub changes: BTreeMap<String, Change>,
+    /// Currencies only present in the newer snapshot, with their rate.
+    pub added: BTreeMap<String, f64>,
+    /// Currencies only present in the older snapshot, with their rate.
+    pub removed: BTreeMap<String, f64>,
+}
+
+impl RateDiff {
+    /// The currencies whose rate moved.
+    pub fn moved(&self) -> BTreeMap<&str, &Change> {
+        self.changes
+            .iter()
+            .filter(|&(_, change)| change.absolute != 0.0)
+            .map(|(currency, change)| (currency.as_ref(), change))
+            .collect()
+    }
+}
+
+impl ExchangeRate {
+    /// Compare these rates with the newer `other`.
+    ///
+    /// If `other` has a different base currency, it is rebased onto the base of `self` first,
+    /// which fails if `other` has no rate for that currency.
+    ///
+    /// The base currency is neither added nor removed when only one snapshot lists it, since its
+    /// rate is 1 either way.
+    pub fn diff(&self, other: &ExchangeRate) -> Result<RateDiff, Error> {
+        let rebased = if other.base != self.base {
+            Some(try!(other.rebase(&self.base)))
+        } else {
+            None
+        };
+        let new = rebased.as_ref().unwrap_or(other);
+
+        let mut diff = RateDiff {
+            base: self.base.clone(),
+            rebased: rebased.is_some(),
+            old_timestamp: self.timestamp,
+            new_timestamp: other.timestamp,
+            changes: BTreeMap::new(),
+            added: BTreeMap::new(),
+            removed: BTreeMap::new(),
+        };
+        for (currency, &old) in &self.rates {
+            match new.rates.get(currency) {
+                Some(&new) => {
+                    diff.changes.insert(currency.clone(), Change::new(old, new));
+                }
+                None if *currency == self.base => {}
+                None => {
+                    diff.removed.insert(currency.clone(), old);
+                }
+            }
+        }
+        for (currency, &rate) in &new.rates {
+            if !self.rates.contains_key(currency) && *currency != self.base {
+                diff.added.insert(currency.clone(), rate);
+            }
+        }
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ExchangeRate;
+    use error::Error;
+    use super::Change;
+
+    #[test]
+    fn diff_same_base() {
+        let monday = ExchangeRate::fixture(1, "USD", &[("EUR", 0.8), ("MYR", 4.0), ("EEK", 12.5)]);
+        let friday = ExchangeRate::fixture(2, "USD", &[("EUR", 0.8), ("MYR", 4.2), ("BTC", 0.002)]);
+
+        let diff = monday.diff(&friday).unwrap();
+        assert!(!diff.rebased);
+        assert_eq!(diff.changes.len(), 2);
+        assert_eq!(diff.changes["MYR"].old, 4.0);
+        assert_eq!(diff.changes["MYR"].new, 4.2);
+        assert!((diff.changes["MYR"].absolute - 0.2).abs() < 1e-12);
+        assert!((diff.changes["MYR"].percent() - 5.0).abs() < 1e-9);
+        assert_eq!(diff.moved().keys().collect::<Vec<_>>(), vec![&"MYR"]);
+        assert_eq!(diff.added.keys().collect::<Vec<_>>(), vec!["BTC"]);
+        assert_eq!(diff.removed.keys().collect::<Vec<_>>(), vec!["EEK"]);
+    }
+
+    #[test]
+    fn diff_rebases() {
+        let usd = ExchangeRate::fixture(1, "USD", &[("EUR", 0.8), ("MYR", 4.0), ("USD", 1.0)]);
+        let eur = ExchangeRate::fixture(2, "EUR", &[("EUR", 1.0), ("MYR", 5.0), ("USD", 1.25)]);
+
+        let diff = usd.diff(&eur).unwrap();
+        assert!(diff.rebased);
+        assert_eq!(diff.base, "USD");
+        assert!(diff.changes["MYR"].absolute.abs() < 1e-12);
+        assert!(diff.changes["EUR"].absolute.abs() < 1e-12);
+        assert!(diff.added.is_empty());
+    }
+
+    #[test]
+    fn diff_ignores_listed_base() {
+        let usd = ExchangeRate::fixture(1, "USD", &[("EUR", 0.8), ("MYR", 4.0)]);
+        let eur = ExchangeRate::fixture(2, "EUR", &[("EUR", 1.0), ("MYR", 5.0), ("USD", 1.25)]);
+
+        let diff = usd.diff(&eur).unwrap();
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(eur.diff(&usd).unwrap().removed.is_empty());
+    }
+
+    #[test]
+    fn diff_cannot_rebase() {
+        let usd = ExchangeRate::fixture(1, "USD", &[("MYR", 4.0)]);
+        let eur = ExchangeRate::fixture(2, "EUR", &[("MYR", 5.0)]);
+
+        match usd.diff(&eur) {
+            Err(Error::MissingRate(currency)) => assert_eq!(currency, "USD"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn change_from_zero() {
+        assert!(Change::new(0.0, 1.0).relative.is_infinite());
+        assert_eq!(Change::new(0.0, 0.0).relative, 0.0);
+    }
+}
@@ -0,0 +1,205 @@
+//! ECB module
+//!
+//! Reads the euro foreign exchange reference rates of the European Central Bank, as published
+//! in the `eurofxref-daily.xml`, `eurofxref-hist-90d.xml` and `eurofxref-hist.xml` files:
+//!
+//! ```xml
+//! <gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" ...>
+//!   <gesmes:subject>Reference rates</gesmes:subject>
+//!   <Cube>
+//!     <Cube time='2016-04-05'>
+//!       <Cube currency='USD' rate='1.1379'/>
+//!       <Cube currency='MYR' rate='4.4591'/>
+//!     </Cube>
+//!   </Cube>
+//! </gesmes:Envelope>
+//! ```
+//!
+//! Each dated `Cube` becomes an `ExchangeRate` with base EUR, timestamped at midnight UTC,
+//! so the conversion and diff helpers work on them as on OpenExchangeRates snapshots. Serve
+//! them through a `provider::StaticProvider` to use them as an offline source.
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use chrono::NaiveDate;
+
+use ExchangeRate;
+use error::Error;
+
+/// Base currency of the reference rates.
+pub const BASE: &'static str = "EUR";
+
+/// Parse a reference rates file into one snapshot per date, from the oldest to the newest.
+pub fn parse(xml: &str) -> Result<Vec<ExchangeRate>, Error> {
+    let mut rates: Vec<ExchangeRate> = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if rest.starts_with("!--") {
+            rest = match rest.find("-->") {
+                Some(end) => &rest[end + 3..],
+                None => return Err(Error::Decode("unterminated XML comment".to_owned())),
+            };
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => return Err(Error::Decode("unterminated XML tag".to_owned())),
+        };
+        let tag = rest[..end].trim_right_matches('/');
+        rest = &rest[end + 1..];
+
+        let (name, attributes) = match tag.find(char::is_whitespace) {
+            Some(index) => (&tag[..index], &tag[index..]),
+            None => (tag, ""),
+        };
+        if name.rsplit(':').next() != Some("Cube") {
+            continue;
+        }
+        let attributes = try!(parse_attributes(attributes));
+
+        if let Some(time) = attributes.get("time") {
+            let date = match NaiveDate::parse_from_str(time, "%Y-%m-%d") {
+                Ok(date) => date,
+                Err(_) => return Err(Error::Decode(format!("invalid time `{}`", time))),
+            };
+            let mut snapshot = BTreeMap::new();
+            snapshot.insert(BASE.to_owned(), 1.0);
+            rates.push(ExchangeRate {
+                disclaimer: String::new(),
+                license: String::new(),
+                timestamp: date.and_hms(0, 0, 0).timestamp(),
+                base: BASE.to_owned(),
+                rates: snapshot,
+            });
+        } else if let (Some(currency), Some(rate)) = (attributes.get("currency"),
+                                                      attributes.get("rate")) {
+            let value = match rate.parse::<f64>() {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(Error::Decode(format!("invalid rate `{}` for {}", rate, currency)))
+                }
+            };
+            match rates.last_mut() {
+                Some(snapshot) => snapshot.rates.insert(currency.clone(), value),
+                None => {
+                    return Err(Error::Decode(format!("rate for {} outside of a date", currency)))
+                }
+            };
+        }
+    }
+
+    rates.sort_by_key(|rate| rate.timestamp);
+    Ok(rates)
+}
+
+/// Read a reference rates file, see `parse`.
+pub fn read<R>(mut reader: R) -> Result<Vec<ExchangeRate>, Error>
+    where R: Read
+{
+    let mut xml = String::new();
+    try!(reader.read_to_string(&mut xml));
+    parse(&xml)
+}
+
+/// Parse a reference rates file and rebase every snapshot to `base`, e.g. USD to compare with
+/// OpenExchangeRates.
+pub fn parse_with_base(xml: &str, base: &str) -> Result<Vec<ExchangeRate>, Error> {
+    let rates = try!(parse(xml));
+    let mut rebased = Vec::with_capacity(rates.len());
+    for rate in &rates {
+        rebased.push(try!(rate.rebase(base)));
+    }
+    Ok(rebased)
+}
+
+/// Parse `name='value'` or `name="value"` pairs.
+fn parse_attributes(s: &str) -> Result<BTreeMap<String, String>, Error> {
+    let mut attributes = BTreeMap::new();
+    let mut rest = s.trim_left();
+    while !rest.is_empty() {
+        let eq = match rest.find('=') {
+            Some(eq) => eq,
+            None => return Err(Error::Decode(format!("invalid XML attributes `{}`", s.trim()))),
+        };
+        let name = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_left();
+        let quote = match value.chars().next() {
+            Some(quote) if quote == '\'' || quote == '"' => quote,
+            _ => return Err(Error::Decode(format!("unquoted XML attribute `{}`", name))),
+        };
+        let end = match value[1..].find(quote) {
+            Some(end) => end + 1,
+            None => return Err(Error::Decode(format!("unterminated XML attribute `{}`", name))),
+        };
+        attributes.insert(name.to_owned(), value[1..end].to_owned());
+        rest = value[end + 1..].trim_left();
+    }
+    Ok(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use error::Error;
+    use super::*;
+
+    const HIST: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+	<gesmes:subject>Reference rates</gesmes:subject>
+	<gesmes:Sender>
+		<gesmes:name>European Central Bank</gesmes:name>
+	</gesmes:Sender>
+	<Cube>
+		<!-- newest first, as published -->
+		<Cube time='2016-04-05'>
+			<Cube currency='USD' rate='1.1379'/>
+			<Cube currency='JPY' rate='125.02'/>
+			<Cube currency='MYR' rate='4.4591'/>
+		</Cube>
+		<Cube time="2016-04-04">
+			<Cube currency="USD" rate="1.1410" />
+			<Cube currency="MYR" rate="4.4515" />
+		</Cube>
+	</Cube>
+</gesmes:Envelope>"#;
+
+    #[test]
+    fn parses_dated_cubes() {
+        let rates = parse(HIST).unwrap();
+        assert_eq!(rates.len(), 2);
+
+        assert_eq!(rates[0].date(), NaiveDate::from_ymd(2016, 4, 4));
+        assert_eq!(rates[0].base, "EUR");
+        assert_eq!(rates[0].rates.len(), 3);
+        assert_eq!(rates[0].rates["USD"], 1.1410);
+
+        assert_eq!(rates[1].date(), NaiveDate::from_ymd(2016, 4, 5));
+        assert_eq!(rates[1].rates["EUR"], 1.0);
+        assert_eq!(rates[1].rates["JPY"], 125.02);
+        assert_eq!(rates[1].convert(100.0, "USD", "MYR"), Some(100.0 / 1.1379 * 4.4591));
+    }
+
+    #[test]
+    fn rebases_to_usd() {
+        let rates = parse_with_base(HIST, "USD").unwrap();
+        assert_eq!(rates[1].base, "USD");
+        assert_eq!(rates[1].rates["USD"], 1.0);
+        assert!((rates[1].rates["EUR"] - 1.0 / 1.1379).abs() < 1e-12);
+        assert!((rates[1].rates["MYR"] - 4.4591 / 1.1379).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(parse("<Cube time='2016-04-05'><Cube currency='USD' rate='abc'/></Cube>").is_err());
+        assert!(parse("<Cube currency='USD' rate='1.1379'/>").is_err());
+        assert!(parse("<Cube time='2016-04-05'><Cube currency='USD rate='1.1'/>").is_err());
+        match parse("<Cube time='yesterday'>") {
+            Err(Error::Decode(msg)) => assert_eq!(msg, "invalid time `yesterday`"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(parse("<gesmes:Envelope></gesmes:Envelope>").unwrap(), Vec::new());
+    }
+}
@@ -3,6 +3,7 @@ use std::error;
 use std::fmt;
 use std::io;
 
+use chrono::{Duration, NaiveDate};
 use hyper;
 use serde_json;
 
@@ -15,6 +16,41 @@ pub enum Error {
     Io(io::Error),
     /// Error comming from `serde_json` crate.
     SerdeJson(serde_json::Error),
+    /// The configuration is missing or contains an invalid value.
+    Config(String),
+    /// The exchange rates are older than the maximum age allowed.
+    Stale {
+        /// Age of the exchange rates when they were received.
+        age: Duration,
+    },
+    /// A CSV file could not be read.
+    Csv(String),
+    /// There is no exchange rate for the currency.
+    MissingRate(String),
+    /// A series has no snapshot for the date.
+    Gap(NaiveDate),
+    /// A response does not have the expected structure.
+    Decode(String),
+    /// A source has no exchange rates to serve.
+    Unavailable(String),
+}
+
+impl Error {
+    /// A short name of the variant, e.g. `hyper` or `stale`, to label metrics and logs with.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            Error::Hyper(_) => "hyper",
+            Error::Io(_) => "io",
+            Error::SerdeJson(_) => "serde_json",
+            Error::Config(_) => "config",
+            Error::Stale { .. } => "stale",
+            Error::Csv(_) => "csv",
+            Error::MissingRate(_) => "missing_rate",
+            Error::Gap(_) => "gap",
+            Error::Decode(_) => "decode",
+            Error::Unavailable(_) => "unavailable",
+        }
+    }
 }
 
 impl error::Error for Error {
@@ -23,6 +59,13 @@ impl error::Error for Error {
             Error::Hyper(ref err) => err.description(),
             Error::Io(ref err) => err.description(),
             Error::SerdeJson(ref err) => err.description(),
+            Error::Config(ref msg) => msg,
+            Error::Stale { .. } => "exchange rates are older than the maximum age allowed",
+            Error::Csv(ref msg) => msg,
+            Error::MissingRate(_) => "no exchange rate for the currency",
+            Error::Gap(_) => "no exchange rates for the date",
+            Error::Decode(ref msg) => msg,
+            Error::Unavailable(ref msg) => msg,
         }
     }
 
@@ -31,6 +74,13 @@ impl error::Error for Error {
             Error::Hyper(ref err) => Some(err),
             Error::Io(ref err) => Some(err),
             Error::SerdeJson(ref err) => Some(err),
+            Error::Config(_) |
+            Error::Stale { .. } |
+            Error::Csv(_) |
+            Error::MissingRate(_) |
+            Error::Gap(_) |
+            Error::Decode(_) |
+            Error::Unavailable(_) => None,
         }
     }
 }
@@ -41,6 +91,15 @@ impl fmt::Display for Error {
             Error::Hyper(ref err) => write!(f, "Hyper error: {}", err),
             Error::Io(ref err) => write!(f, "IO error: {}", err),
             Error::SerdeJson(ref err) => write!(f, "Serde JSON error: {}", err),
+            Error::Config(ref msg) => write!(f, "Config error: {}", msg),
+            Error::Stale { ref age } => {
+                write!(f, "Stale error: rates are {} seconds old", age.num_seconds())
+            }
+            Error::Csv(ref msg) => write!(f, "CSV error: {}", msg),
+            Error::MissingRate(ref currency) => write!(f, "No exchange rate for {}", currency),
+            Error::Gap(ref date) => write!(f, "Gap error: no exchange rates for {}", date),
+            Error::Decode(ref msg) => write!(f, "Decode error: {}", msg),
+            Error::Unavailable(ref msg) => write!(f, "Unavailable error: {}", msg),
         }
     }
 }
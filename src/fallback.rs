@@ -0,0 +1,200 @@
+//! Fallback module
+//!
+//! A `FallbackProvider` asks a list of sources in turn until one answers, and reports which
+//! one did:
+//!
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use openexchangerates::{Client, csv};
+//! use openexchangerates::fallback::FallbackProvider;
+//! use openexchangerates::provider::StaticProvider;
+//!
+//! let snapshot = csv::read_wide(File::open("rates.csv").unwrap()).unwrap();
+//! let provider = FallbackProvider::new()
+//!     .source("openexchangerates", Client::new("YOUR_APP_ID"))
+//!     .source("snapshot", StaticProvider::new(snapshot));
+//!
+//! let served = provider.latest_served().unwrap();
+//! println!("rates from {}, {} minutes old", served.source, served.age.num_minutes());
+//! ```
+use chrono::{Duration, NaiveDate};
+
+use {Currencies, ExchangeRate};
+use error::Error;
+use provider::RateProvider;
+
+/// Exchange rates along with the source that served them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Served {
+    pub rate: ExchangeRate,
+    /// Name of the source.
+    pub source: String,
+    /// Age of the rates when they were served.
+    pub age: Duration,
+    /// The sources tried before, with their errors.
+    pub failures: Vec<(String, String)>,
+}
+
+/// Tries its sources in order, returning the first successful result.
+pub struct FallbackProvider<'a> {
+    sources: Vec<(String, Box<RateProvider + 'a>)>,
+}
+
+impl<'a> Default for FallbackProvider<'a> {
+    fn default() -> FallbackProvider<'a> {
+        FallbackProvider::new()
+    }
+}
+
+impl<'a> FallbackProvider<'a> {
+    /// Create a provider without sources, which fails with `Error::Unavailable`.
+    pub fn new() -> FallbackProvider<'a> {
+        FallbackProvider { sources: Vec::new() }
+    }
+
+    /// Try `provider`, named `name`, after the sources added before.
+    pub fn source<S, P>(mut self, name: S, provider: P) -> FallbackProvider<'a>
+        where S: Into<String>,
+              P: RateProvider + 'a
+    {
+        self.sources.push((name.into(), Box::new(provider)));
+        self
+    }
+
+    /// The latest exchange rates of the first source that has them.
+    pub fn latest_served(&self) -> Result<Served, Error> {
+        self.serve(|provider| provider.latest())
+    }
+
+    /// The exchange rates at the end of `date` of the first source that has them.
+    pub fn historical_served(&self, date: NaiveDate) -> Result<Served, Error> {
+        self.serve(|provider| provider.historical(date))
+    }
+
+    fn serve<F>(&self, f: F) -> Result<Served, Error>
+        where F: Fn(&RateProvider) -> Result<ExchangeRate, Error>
+    {
+        let mut failures = Vec::new();
+        for &(ref name, ref provider) in &self.sources {
+            match f(&**provider) {
+                Ok(rate) => {
+                    return Ok(Served {
+                        age: rate.age(),
+                        rate: rate,
+                        source: name.clone(),
+                        failures: failures,
+                    })
+                }
+                Err(err) => failures.push((name.clone(), err.to_string())),
+            }
+        }
+        Err(unavailable(&failures))
+    }
+}
+
+impl<'a> RateProvider for FallbackProvider<'a> {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        self.latest_served().map(|served| served.rate)
+    }
+
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        self.historical_served(date).map(|served| served.rate)
+    }
+
+    /// The currencies of the first source that has them.
+    fn currencies(&self) -> Result<Currencies, Error> {
+        let mut failures = Vec::new();
+        for &(ref name, ref provider) in &self.sources {
+            match provider.currencies() {
+                Ok(currencies) => return Ok(currencies),
+                Err(err) => failures.push((name.clone(), err.to_string())),
+            }
+        }
+        Err(unavailable(&failures))
+    }
+}
+
+fn unavailable(failures: &[(String, String)]) -> Error {
+    if failures.is_empty() {
+        return Error::Unavailable("no sources".to_owned());
+    }
+    let failures: Vec<String> = failures.iter()
+        .map(|&(ref name, ref err)| format!("{}: {}", name, err))
+        .collect();
+    Error::Unavailable(format!("every source failed ({})", failures.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, UTC};
+
+    use {Currencies, ExchangeRate};
+    use error::Error;
+    use provider::{RateProvider, StaticProvider};
+    use super::*;
+
+    struct Offline;
+
+    impl RateProvider for Offline {
+        fn latest(&self) -> Result<ExchangeRate, Error> {
+            Err(Error::Unavailable("offline".to_owned()))
+        }
+
+        fn historical(&self, _: NaiveDate) -> Result<ExchangeRate, Error> {
+            Err(Error::Unavailable("offline".to_owned()))
+        }
+
+        fn currencies(&self) -> Result<Currencies, Error> {
+            Err(Error::Unavailable("offline".to_owned()))
+        }
+    }
+
+    fn rate(timestamp: i64) -> ExchangeRate {
+        ExchangeRate::fixture(timestamp, "USD", &[("MYR", 3.9)])
+    }
+
+    #[test]
+    fn falls_back_in_order() {
+        let an_hour_ago = (UTC::now() - Duration::hours(1)).timestamp();
+        let provider = FallbackProvider::new()
+            .source("openexchangerates", Offline)
+            .source("cache", StaticProvider::new(Vec::new()))
+            .source("snapshot", StaticProvider::new(vec![rate(an_hour_ago)]))
+            .source("unused", Offline);
+
+        let served = provider.latest_served().unwrap();
+        assert_eq!(served.source, "snapshot");
+        assert_eq!(served.rate.timestamp, an_hour_ago);
+        assert!(served.age >= Duration::hours(1) && served.age < Duration::hours(2));
+        assert_eq!(served.failures.len(), 2);
+        assert_eq!(served.failures[0],
+                   ("openexchangerates".to_owned(), "Unavailable error: offline".to_owned()));
+        assert_eq!(served.failures[1].0, "cache");
+    }
+
+    #[test]
+    fn historical_skips_sources_without_the_date() {
+        let provider = FallbackProvider::new()
+            .source("recent", StaticProvider::new(vec![rate(1459854002)]))
+            .source("archive", StaticProvider::new(vec![rate(1361055600)]));
+
+        let served = provider.historical_served(NaiveDate::from_ymd(2013, 2, 16)).unwrap();
+        assert_eq!(served.source, "archive");
+        assert_eq!(served.failures[0].0, "recent");
+    }
+
+    #[test]
+    fn fails_when_every_source_fails() {
+        let provider = FallbackProvider::new().source("a", Offline).source("b", Offline);
+        match provider.latest() {
+            Err(Error::Unavailable(msg)) => {
+                assert_eq!(msg,
+                           "every source failed (a: Unavailable error: offline; b: Unavailable \
+                            error: offline)")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(FallbackProvider::new().currencies().is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Features module
+//!
+//! `Features` is decoded by hand so that flags added to plans by OpenExchangeRates end up in
+//! `Features::extra` instead of failing the whole `usage` response.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error;
+use serde_json::Value;
+
+use Features;
+
+/// Names of the flags with their own field in `Features`.
+pub const KNOWN: [&'static str; 5] = ["base", "symbols", "experimental", "time-series", "convert"];
+
+impl Features {
+    /// The flag named `name`, whether known or extra.
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "base" => Some(self.base),
+            "symbols" => Some(self.symbols),
+            "experimental" => Some(self.experimental),
+            "time-series" => Some(self.time_series),
+            "convert" => Some(self.convert),
+            _ => self.extra.get(name).cloned(),
+        }
+    }
+
+    /// Every flag by name, as sent by the API.
+    pub fn flags(&self) -> BTreeMap<String, bool> {
+        let mut flags = self.extra.clone();
+        for name in &KNOWN {
+            flags.insert((*name).to_owned(), self.get(name).unwrap_or(false));
+        }
+        flags
+    }
+}
+
+impl Serialize for Features {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+        where S: Serializer
+    {
+        self.flags().serialize(serializer)
+    }
+}
+
+impl Deserialize for Features {
+    /// Decode the known flags, which are required, and keep the other boolean flags in
+    /// `extra`. Values of other types are ignored, like unknown fields elsewhere.
+    fn deserialize<D>(deserializer: &mut D) -> Result<Features, D::Error>
+        where D: Deserializer
+    {
+        let mut values: BTreeMap<String, Value> = try!(Deserialize::deserialize(deserializer));
+
+        let mut known = [false; 5];
+        for (flag, name) in known.iter_mut().zip(&KNOWN) {
+            *flag = match values.remove(*name) {
+                Some(Value::Bool(value)) => value,
+                Some(_) => {
+                    return Err(D::Error::invalid_value(&format!("`{}` is not a boolean", name)))
+                }
+                None => return Err(D::Error::missing_field(*name)),
+            };
+        }
+
+        Ok(Features {
+            base: known[0],
+            symbols: known[1],
+            experimental: known[2],
+            time_series: known[3],
+            convert: known[4],
+            extra: values.into_iter()
+                .filter_map(|(name, value)| value.as_bool().map(|value| (name, value)))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use Features;
+
+    #[test]
+    fn keeps_extra_flags() {
+        let features: Features = serde_json::from_str(r#"{
+            "base": true, "symbols": true, "experimental": true, "time-series": false,
+            "convert": false, "bid-ask": true, "ohlc": false, "note": "new"
+        }"#)
+            .unwrap();
+        assert!(features.base);
+        assert!(!features.time_series);
+        assert_eq!(features.extra.len(), 2);
+        assert_eq!(features.get("bid-ask"), Some(true));
+        assert_eq!(features.get("ohlc"), Some(false));
+        assert_eq!(features.get("note"), None);
+
+        let encoded = serde_json::to_string(&features).unwrap();
+        assert_eq!(serde_json::from_str::<Features>(&encoded).unwrap(), features);
+    }
+
+    #[test]
+    fn requires_known_flags() {
+        assert!(serde_json::from_str::<Features>(r#"{"base": true}"#).is_err());
+        assert!(serde_json::from_str::<Features>(r#"{
+            "base": 1, "symbols": true, "experimental": true, "time-series": false,
+            "convert": false
+        }"#)
+            .is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! Lenient module
+//!
+//! Strict decoding fails on any surprise in a response, such as a `null` rate. The functions
+//! here decode from a `serde_json::Value` instead, working around what they can and recording
+//! a `Warning` for each workaround:
+//!
+//! * rates that are `null` or not numbers are skipped;
+//! * missing or mistyped descriptive fields, such as `disclaimer` or the usage figures, are
+//!   replaced by empty strings, zeros and `false`.
+//!
+//! Unknown fields are ignored. Only the fields without which the result would be meaningless,
+//! such as `timestamp`, `base` and `rates`, are still required.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::u32;
+
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+
+use {Client, ExchangeRate, Features, Usage, UsageData, UsageDataPlan, UsageDataUsage};
+use error::Error;
+use features::KNOWN;
+use timestamp;
+
+/// A decoded value and the problems worked around while decoding it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lenient<T> {
+    pub data: T,
+    pub warnings: Vec<Warning>,
+}
+
+/// A problem in a response that lenient decoding worked around.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    /// Dotted path of the field, e.g. `rates.MYR`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Decode an `ExchangeRate` leniently.
+pub fn exchange_rate(value: &Value) -> Result<Lenient<ExchangeRate>, Error> {
+    let mut decoder = Decoder { warnings: Vec::new() };
+    let object = try!(as_object(value, "response"));
+
+    let timestamp = match try!(required(object, "", "timestamp")).as_i64() {
+        Some(timestamp) => try!(timestamp::check(timestamp)),
+        None => return Err(Error::Decode("`timestamp` is not an integer".to_owned())),
+    };
+    let base = match try!(required(object, "", "base")).as_str() {
+        Some(base) => base.to_owned(),
+        None => return Err(Error::Decode("`base` is not a string".to_owned())),
+    };
+
+    let mut rates = BTreeMap::new();
+    for (currency, value) in try!(object_field(object, "", "rates")) {
+        match value.as_f64() {
+            Some(rate) => {
+                rates.insert(currency.clone(), rate);
+            }
+            None => {
+                let message = format!("skipped {} rate", describe(value));
+                decoder.warn(join("rates", currency), message);
+            }
+        }
+    }
+
+    let rate = ExchangeRate {
+        disclaimer: decoder.string(object, "", "disclaimer"),
+        license: decoder.string(object, "", "license"),
+        timestamp: timestamp,
+        base: base,
+        rates: rates,
+    };
+    Ok(decoder.finish(rate))
+}
+
+/// Decode a `Usage` leniently.
+pub fn usage(value: &Value) -> Result<Lenient<Usage>, Error> {
+    let mut decoder = Decoder { warnings: Vec::new() };
+    let object = try!(as_object(value, "response"));
+    let data = try!(object_field(object, "", "data"));
+    let plan = try!(object_field(data, "data", "plan"));
+    let features = try!(object_field(plan, "data.plan", "features"));
+    let counts = try!(object_field(data, "data", "usage"));
+
+    let usage = Usage {
+        status: decoder.unsigned(object, "", "status"),
+        data: UsageData {
+            app_id: decoder.string(data, "data", "app_id"),
+            status: decoder.string(data, "data", "status"),
+            plan: UsageDataPlan {
+                name: decoder.string(plan, "data.plan", "name"),
+                quota: decoder.string(plan, "data.plan", "quota"),
+                update_frequency: decoder.string(plan, "data.plan", "update_frequency"),
+                features: Features {
+                    base: decoder.boolean(features, "data.plan.features", "base"),
+                    symbols: decoder.boolean(features, "data.plan.features", "symbols"),
+                    experimental: decoder.boolean(features, "data.plan.features", "experimental"),
+                    time_series: decoder.boolean(features, "data.plan.features", "time-series"),
+                    convert: decoder.boolean(features, "data.plan.features", "convert"),
+                    extra: features.iter()
+                        .filter(|&(name, _)| !KNOWN.iter().any(|known| *known == &name[..]))
+                        .filter_map(|(name, value)| value.as_bool().map(|b| (name.clone(), b)))
+                        .collect(),
+                },
+            },
+            usage: UsageDataUsage {
+                requests: decoder.integer(counts, "data.usage", "requests"),
+                requests_quota: decoder.integer(counts, "data.usage", "requests_quota"),
+                requests_remaining: decoder.integer(counts, "data.usage", "requests_remaining"),
+                days_elapsed: decoder.integer(counts, "data.usage", "days_elapsed"),
+                days_remaining: decoder.integer(counts, "data.usage", "days_remaining"),
+                daily_average: decoder.integer(counts, "data.usage", "daily_average"),
+            },
+        },
+    };
+    Ok(decoder.finish(usage))
+}
+
+impl<'a> Client<'a> {
+    /// Like `latest`, decoding leniently.
+    pub fn latest_lenient(&self) -> Result<Lenient<ExchangeRate>, Error> {
+        let max_age = self.max_age;
+        self.get_lenient(&self.url("latest.json", true), |value| {
+            let lenient = try!(exchange_rate(value));
+            if let Some(max_age) = max_age {
+                try!(lenient.data.check_age(max_age));
+            }
+            Ok(lenient)
+        })
+    }
+
+    /// Like `historical`, decoding leniently.
+    pub fn historical_lenient(&self, date: NaiveDate) -> Result<Lenient<ExchangeRate>, Error> {
+        let url = self.url(&format!("historical/{}.json", date.format("%Y-%m-%d")), true);
+        self.get_lenient(&url, exchange_rate)
+    }
+
+    /// Like `usage`, decoding leniently.
+    pub fn usage_lenient(&self) -> Result<Lenient<Usage>, Error> {
+        self.get_lenient(&self.url("usage.json", false), usage)
+    }
+
+    /// Get `url` and decode it with `decode`, so that the metrics record its errors too.
+    fn get_lenient<T, F>(&self, url: &str, decode: F) -> Result<Lenient<T>, Error>
+        where F: FnOnce(&Value) -> Result<Lenient<T>, Error>
+    {
+        let mut decoded = None;
+        try!(self.get_checked(url, |value: &Value| {
+            decoded = Some(try!(decode(value)));
+            Ok(())
+        }));
+        Ok(decoded.expect("`decode` succeeded"))
+    }
+}
+
+struct Decoder {
+    warnings: Vec<Warning>,
+}
+
+impl Decoder {
+    fn warn(&mut self, path: String, message: String) {
+        self.warnings.push(Warning {
+            path: path,
+            message: message,
+        });
+    }
+
+    fn finish<T>(self, data: T) -> Lenient<T> {
+        Lenient {
+            data: data,
+            warnings: self.warnings,
+        }
+    }
+
+    fn string(&mut self, object: &Map<String, Value>, path: &str, key: &str) -> String {
+        match object.get(key).and_then(|value| value.as_str()) {
+            Some(s) => s.to_owned(),
+            None => {
+                let message = format!("replaced {} with an empty string", found(object, key));
+                self.warn(join(path, key), message);
+                String::new()
+            }
+        }
+    }
+
+    fn integer(&mut self, object: &Map<String, Value>, path: &str, key: &str) -> i64 {
+        match object.get(key).and_then(|value| value.as_i64()) {
+            Some(n) => n,
+            None => {
+                self.warn(join(path, key), format!("replaced {} with 0", found(object, key)));
+                0
+            }
+        }
+    }
+
+    fn unsigned(&mut self, object: &Map<String, Value>, path: &str, key: &str) -> u32 {
+        let n = self.integer(object, path, key);
+        if n < 0 || n > u32::MAX as i64 {
+            self.warn(join(path, key), format!("replaced out-of-range `{}` with 0", n));
+            return 0;
+        }
+        n as u32
+    }
+
+    fn boolean(&mut self, object: &Map<String, Value>, path: &str, key: &str) -> bool {
+        match object.get(key).and_then(|value| value.as_bool()) {
+            Some(b) => b,
+            None => {
+                self.warn(join(path, key), format!("replaced {} with false", found(object, key)));
+                false
+            }
+        }
+    }
+}
+
+fn as_object<'v>(value: &'v Value, path: &str) -> Result<&'v Map<String, Value>, Error> {
+    value.as_object().ok_or_else(|| Error::Decode(format!("`{}` is not an object", path)))
+}
+
+fn required<'v>(object: &'v Map<String, Value>,
+                path: &str,
+                key: &str)
+                -> Result<&'v Value, Error> {
+    object.get(key).ok_or_else(|| Error::Decode(format!("missing `{}`", join(path, key))))
+}
+
+/// A nested object, which is required.
+fn object_field<'v>(object: &'v Map<String, Value>,
+                    path: &str,
+                    key: &str)
+                    -> Result<&'v Map<String, Value>, Error> {
+    as_object(try!(required(object, path, key)), &join(path, key))
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Describe the value of `key` in `object` for a warning.
+fn found(object: &Map<String, Value>, key: &str) -> String {
+    match object.get(key) {
+        Some(value) => describe(value),
+        None => "missing value".to_owned(),
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match *value {
+        Value::Null => "null".to_owned(),
+        Value::String(ref s) => format!("non-numeric `{}`", s),
+        _ => format!("unexpected `{}`", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use serde_json::Value;
+
+    use error::Error;
+    use super::*;
+
+    fn parse(s: &str) -> Value {
+        serde_json::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn exchange_rate_skips_bad_rates() {
+        let value = parse(r#"{
+            "timestamp": 1459854002,
+            "base": "USD",
+            "new_field": [1, 2],
+            "rates": { "EUR": 0.880403, "MYR": null, "SGD": "1.35", "XAU": 1 }
+        }"#);
+
+        let lenient = exchange_rate(&value).unwrap();
+        assert_eq!(lenient.data.timestamp, 1459854002);
+        assert_eq!(lenient.data.rates.len(), 2);
+        assert_eq!(lenient.data.rates["XAU"], 1.0);
+        assert_eq!(lenient.warnings.len(), 4);
+        assert_eq!(lenient.warnings[0].to_string(), "rates.MYR: skipped null rate");
+        assert_eq!(lenient.warnings[1].to_string(), "rates.SGD: skipped non-numeric `1.35` rate");
+        assert_eq!(lenient.warnings[2].path, "disclaimer");
+        assert_eq!(lenient.warnings[3].path, "license");
+    }
+
+    #[test]
+    fn exchange_rate_requires_rates() {
+        match exchange_rate(&parse(r#"{"timestamp": 1, "base": "USD"}"#)) {
+            Err(Error::Decode(msg)) => assert_eq!(msg, "missing `rates`"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(exchange_rate(&parse(r#"{"timestamp": "now", "base": "USD", "rates": {}}"#))
+            .is_err());
+        let huge = r#"{"timestamp": 9223372036854775807, "base": "USD", "rates": {}}"#;
+        match exchange_rate(&parse(huge)) {
+            Err(Error::Decode(msg)) => assert!(msg.contains("out of range")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn usage_fills_missing_values() {
+        let value = parse(r#"{
+            "status": 200,
+            "data": {
+                "app_id": "1234",
+                "status": "active",
+                "plan": {
+                    "name": "Forever Free",
+                    "quota": "1,000 requests/month",
+                    "update_frequency": "hourly",
+                    "features": { "base": false, "symbols": false, "experimental": true,
+                                  "time-series": false, "convert": false, "bid-ask": true }
+                },
+                "usage": {
+                    "requests": 11, "requests_quota": 1000, "requests_remaining": null,
+                    "days_elapsed": 10, "days_remaining": 20, "daily_average": 1
+                }
+            }
+        }"#);
+
+        let lenient = usage(&value).unwrap();
+        assert_eq!(lenient.data.data.usage.requests, 11);
+        assert_eq!(lenient.data.data.usage.requests_remaining, 0);
+        assert!(lenient.data.data.plan.features.experimental);
+        assert_eq!(lenient.warnings.len(), 1);
+        assert_eq!(lenient.warnings[0].to_string(),
+                   "data.usage.requests_remaining: replaced null with 0");
+    }
+
+    #[test]
+    fn usage_replaces_out_of_range_status() {
+        let value = parse(r#"{
+            "status": 4294967296,
+            "data": { "plan": { "features": {} }, "usage": {} }
+        }"#);
+
+        let lenient = usage(&value).unwrap();
+        assert_eq!(lenient.data.status, 0);
+        assert_eq!(lenient.warnings[0].to_string(),
+                   "status: replaced out-of-range `4294967296` with 0");
+    }
+}
@@ -4,6 +4,7 @@
 #[cfg_attr(feature = "serde_macros", plugin(serde_macros))]
 
 extern crate chrono;
+extern crate crossbeam;
 extern crate hyper;
 extern crate serde;
 extern crate serde_json;
@@ -13,12 +14,40 @@ extern crate serde_json;
 extern crate yup_hyper_mock;
 
 use std::borrow::Cow;
+use std::cmp;
 use std::collections::BTreeMap;
-use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use chrono::*;
 
+use metrics::Metrics;
+use response::{Meta, Response};
+use time_series::TimeSeries;
+
+pub mod analytics;
+pub mod bid_ask;
+pub mod calendar;
+pub mod catalog;
+pub mod config;
+pub mod convert;
+pub mod csv;
+pub mod diff;
+pub mod ecb;
 pub mod error;
+pub mod fallback;
+pub mod features;
+pub mod lenient;
+pub mod metrics;
+pub mod provider;
+pub mod reconcile;
+pub mod response;
+pub mod snapshot;
+pub mod time_series;
+pub mod timestamp;
+pub mod watcher;
 
 #[cfg(feature = "serde_macros")]
 include!("serde_types.in.rs");
@@ -26,8 +55,45 @@ include!("serde_types.in.rs");
 #[cfg(feature = "serde_codegen")]
 include!(concat!(env!("OUT_DIR"), "/serde_types.rs"));
 
+#[cfg(test)]
+impl ExchangeRate {
+    /// Rates of `base` at `timestamp`, without disclaimer and license, for the tests.
+    fn fixture(timestamp: i64, base: &str, rates: &[(&str, f64)]) -> ExchangeRate {
+        ExchangeRate {
+            disclaimer: String::new(),
+            license: String::new(),
+            timestamp: timestamp,
+            base: base.to_owned(),
+            rates: rates.iter().map(|&(currency, rate)| (currency.to_owned(), rate)).collect(),
+        }
+    }
+}
+
+/// Default location of the OpenExchangeRates API.
+pub const DEFAULT_BASE_URL: &'static str = "https://openexchangerates.org/api";
+
+/// Default number of concurrent requests of `Client::historical_many`.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Options of `Client::currencies_with`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CurrenciesOptions {
+    /// Include digital, metal and unofficial currencies.
+    pub show_alternative: bool,
+    /// Include currencies that are no longer in circulation.
+    pub show_inactive: bool,
+}
+
 pub struct Client<'a> {
     app_id: Cow<'a, str>,
+    base_url: Cow<'a, str>,
+    base: Option<Cow<'a, str>>,
+    symbols: Option<String>,
+    max_age: Option<chrono::Duration>,
+    concurrency: usize,
+    min_interval: Option<Duration>,
+    last_request: Mutex<Option<Instant>>,
+    metrics: Option<Arc<Metrics>>,
     hc: hyper::Client,
 }
 
@@ -38,81 +104,444 @@ impl<'a> Client<'a> {
     {
         Client {
             app_id: app_id.into(),
+            base_url: Cow::Borrowed(DEFAULT_BASE_URL),
+            base: None,
+            symbols: None,
+            max_age: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            min_interval: None,
+            last_request: Mutex::new(None),
+            metrics: None,
             hc: hyper::Client::new(),
         }
     }
 
+    /// Use a different location for the API, e.g. a proxy or a mirror.
+    pub fn base_url<S>(mut self, base_url: S) -> Client<'a>
+        where S: Into<Cow<'a, str>>
+    {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Request the exchange rates relative to `base` instead of USD.
+    ///
+    /// Changing the base currency requires a paid plan.
+    pub fn base<S>(mut self, base: S) -> Client<'a>
+        where S: Into<Cow<'a, str>>
+    {
+        self.base = Some(base.into());
+        self
+    }
+
+    /// Only request the exchange rates of `symbols` instead of every currency.
+    pub fn symbols<I, S>(mut self, symbols: I) -> Client<'a>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        let symbols: Vec<String> = symbols.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        self.symbols = Some(symbols.join(","));
+        self
+    }
+
+    /// Set the read and write timeout of the underlying HTTP client.
+    pub fn timeout(mut self, timeout: Duration) -> Client<'a> {
+        self.hc.set_read_timeout(Some(timeout));
+        self.hc.set_write_timeout(Some(timeout));
+        self
+    }
+
+    /// Reject latest exchange rates that are older than `max_age` with `Error::Stale`.
+    ///
+    /// Use this to avoid pricing with a snapshot from a stalled upstream feed.
+    pub fn max_age(mut self, max_age: chrono::Duration) -> Client<'a> {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Make at most `concurrency` requests at once in `historical_many`.
+    pub fn concurrency(mut self, concurrency: usize) -> Client<'a> {
+        self.concurrency = cmp::max(concurrency, 1);
+        self
+    }
+
+    /// Make at most `requests_per_second` requests per second, waiting before a request if
+    /// needed. This applies to every endpoint, including the concurrent requests of
+    /// `historical_many`.
+    pub fn rate_limit(mut self, requests_per_second: u32) -> Client<'a> {
+        self.min_interval = if requests_per_second == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(1) / requests_per_second)
+        };
+        self
+    }
+
+    /// Record every request in `metrics`, see the `metrics` module.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Client<'a> {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Get the latest exchange rates.
     ///
     /// The corresponding endpoint in OpenExchangeRates is documented in [here](https://docs.openexchangerates.org/docs/latest-json).
-    pub fn latest(self) -> Result<ExchangeRate, error::Error> {
-        let url = &format!("https://openexchangerates.org/api/latest.json?app_id={}",
-                           self.app_id);
-        let mut res = try!(self.hc.get(url).send());
+    pub fn latest(&self) -> Result<ExchangeRate, error::Error> {
+        self.latest_with_meta().map(|res| res.data)
+    }
 
-        let mut body = String::new();
-        try!(res.read_to_string(&mut body));
+    /// Like `latest`, along with the metadata of the response.
+    pub fn latest_with_meta(&self) -> Result<Response<ExchangeRate>, error::Error> {
+        let url = &self.url("latest.json", true);
+        let max_age = self.max_age;
+        self.get_checked(url, |rate: &ExchangeRate| {
+            match max_age {
+                Some(max_age) => rate.check_age(max_age),
+                None => Ok(()),
+            }
+        })
+    }
 
-        let deserialized: ExchangeRate = try!(serde_json::from_str(&body));
-        Ok(deserialized)
+    /// Get the latest exchange rates as bid, ask and mid quotes.
+    ///
+    /// This sets `show_bid_ask`, which is only available on some plans.
+    pub fn latest_bid_ask(&self) -> Result<BidAskRate, error::Error> {
+        self.latest_bid_ask_with_meta().map(|res| res.data)
+    }
+
+    /// Like `latest_bid_ask`, along with the metadata of the response.
+    pub fn latest_bid_ask_with_meta(&self) -> Result<Response<BidAskRate>, error::Error> {
+        let url = self.url("latest.json", true) + "&show_bid_ask=1";
+        let max_age = self.max_age;
+        self.get_checked(&url, |rate: &BidAskRate| {
+            match max_age {
+                Some(max_age) => rate.check_age(max_age),
+                None => Ok(()),
+            }
+        })
     }
 
     /// Get a list of supported currencies.
     ///
     /// The corresponding endpoint in OpenExchangeRates is documented in [here](https://docs.openexchangerates.org/docs/currencies-json).
-    pub fn currencies(self) -> Result<Currencies, error::Error> {
-        let url = &format!("https://openexchangerates.org/api/currencies.json?app_id={}",
-                           self.app_id);
-        let mut res = try!(self.hc.get(url).send());
+    pub fn currencies(&self) -> Result<Currencies, error::Error> {
+        self.currencies_with_meta().map(|res| res.data)
+    }
+
+    /// Like `currencies`, along with the metadata of the response.
+    pub fn currencies_with_meta(&self) -> Result<Response<Currencies>, error::Error> {
+        let url = &self.url("currencies.json", false);
+        self.get_with_meta(url)
+    }
 
-        let mut body = String::new();
-        try!(res.read_to_string(&mut body));
+    /// Get a list of supported currencies, with alternative or inactive ones if asked for.
+    ///
+    /// Each currency comes with its metadata from the `catalog`, if it is listed there.
+    pub fn currencies_with(&self,
+                           options: &CurrenciesOptions)
+                           -> Result<BTreeMap<String, catalog::Currency>, error::Error> {
+        self.currencies_with_options_meta(options).map(|res| res.data)
+    }
 
-        let deserialized: Currencies = try!(serde_json::from_str(&body));
-        Ok(deserialized)
+    /// Like `currencies_with`, along with the metadata of the response.
+    ///
+    /// This is not named `currencies_with_meta`, which is the variant of `currencies`.
+    pub fn currencies_with_options_meta
+        (&self,
+         options: &CurrenciesOptions)
+         -> Result<Response<BTreeMap<String, catalog::Currency>>, error::Error> {
+        let res: Response<Currencies> = try!(self.get_with_meta(&self.currencies_url(options)));
+        Ok(res.map(|currencies| catalog::merge(&currencies)))
     }
 
     /// Get the exchange rate for a particular date.
     ///
     /// The corresponding endpoint in OpenExchangeRates is documented in [here](https://docs.openexchangerates.org/docs/historical-json).
-    pub fn historical(self, date: NaiveDate) -> Result<ExchangeRate, error::Error> {
-        let url = &format!("https://openexchangerates.org/api/historical/{}.json?app_id={}",
-                           date.format("%Y-%m-%d"),
-                           self.app_id);
-        let mut res = try!(self.hc.get(url).send());
+    pub fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, error::Error> {
+        self.historical_with_meta(date).map(|res| res.data)
+    }
 
-        let mut body = String::new();
-        try!(res.read_to_string(&mut body));
+    /// Like `historical`, along with the metadata of the response.
+    pub fn historical_with_meta(&self,
+                                date: NaiveDate)
+                                -> Result<Response<ExchangeRate>, error::Error> {
+        let url = &self.url(&format!("historical/{}.json", date.format("%Y-%m-%d")), true);
+        self.get_with_meta(url)
+    }
 
-        let deserialized: ExchangeRate = try!(serde_json::from_str(&body));
-        Ok(deserialized)
+    /// Get the exchange rates for several dates, fetching up to `concurrency` of them at once.
+    ///
+    /// The results are in the order of `dates`, and a failed date does not stop the others.
+    pub fn historical_many(&self,
+                           dates: &[NaiveDate])
+                           -> Vec<Result<ExchangeRate, error::Error>> {
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<Result<ExchangeRate, error::Error>>>> =
+            Mutex::new(dates.iter().map(|_| None).collect());
+
+        crossbeam::scope(|scope| {
+            for _ in 0..cmp::min(self.concurrency, dates.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        if index >= dates.len() {
+                            break;
+                        }
+                        let result = self.historical(dates[index]);
+                        results.lock().unwrap()[index] = Some(result);
+                    }
+                });
+            }
+        });
+
+        results.into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.expect("every date is fetched"))
+            .collect()
+    }
+
+    /// Get the exchange rate for a particular date as bid, ask and mid quotes.
+    ///
+    /// This sets `show_bid_ask`, which is only available on some plans.
+    pub fn historical_bid_ask(&self, date: NaiveDate) -> Result<BidAskRate, error::Error> {
+        self.historical_bid_ask_with_meta(date).map(|res| res.data)
+    }
+
+    /// Like `historical_bid_ask`, along with the metadata of the response.
+    pub fn historical_bid_ask_with_meta(&self,
+                                        date: NaiveDate)
+                                        -> Result<Response<BidAskRate>, error::Error> {
+        let url = self.url(&format!("historical/{}.json", date.format("%Y-%m-%d")), true) +
+                  "&show_bid_ask=1";
+        self.get_with_meta(&url)
+    }
+
+    /// Get the exchange rates of every day from `start` to `end`, as an iterator that decodes
+    /// one day at a time while reading the response.
+    ///
+    /// The request is recorded in the metrics once the series is read to the end or dropped, so
+    /// that its latency includes the decoding.
+    ///
+    /// The corresponding endpoint in OpenExchangeRates is documented in [here](https://docs.openexchangerates.org/docs/time-series-json).
+    pub fn time_series(&self,
+                       start: NaiveDate,
+                       end: NaiveDate)
+                       -> Result<TimeSeries<hyper::client::Response>, error::Error> {
+        self.time_series_with_meta(start, end).map(|res| res.data)
+    }
+
+    /// Like `time_series`, along with the metadata of the response. As the days are read by
+    /// the iterator, `meta.latency` only covers the response up to its first day.
+    pub fn time_series_with_meta(&self,
+                                 start: NaiveDate,
+                                 end: NaiveDate)
+                                 -> Result<Response<TimeSeries<hyper::client::Response>>,
+                                           error::Error> {
+        let url = self.url("time-series.json", true) +
+                  &format!("&start={}&end={}", start.format("%Y-%m-%d"), end.format("%Y-%m-%d"));
+        let base = self.base.as_ref().map_or("USD", |base| base.as_ref());
+        self.throttle();
+        let start = Instant::now();
+        let mut status = None;
+        let result = self.hc.get(&url).send().map_err(error::Error::from).and_then(|res| {
+            let code = res.status.to_u16();
+            status = Some(code);
+            let headers = res.headers.clone();
+            let series = try!(TimeSeries::new(res, base));
+            Ok(Response {
+                data: series,
+                meta: Meta::new(&url, code, start.elapsed(), &headers),
+            })
+        });
+
+        let metrics = match self.metrics {
+            Some(ref metrics) => metrics.clone(),
+            None => return result,
+        };
+        match result {
+            Ok(res) => {
+                Ok(res.map(|series| {
+                    series.on_finish(move |error| {
+                        metrics.observe("time-series", status, start.elapsed(), error)
+                    })
+                }))
+            }
+            Err(err) => {
+                metrics.observe("time-series", status, start.elapsed(), Some(&err));
+                Err(err)
+            }
+        }
     }
 
     /// Get statistics about your App ID.
     ///
     /// The corresponding endpoint in OpenExchangeRates is documented in [here](https://docs.openexchangerates.org/docs/usage-json).
-    pub fn usage(self) -> Result<Usage, error::Error> {
-        let url = &format!("https://openexchangerates.org/api/usage.json?app_id={}",
-                           self.app_id);
-        let mut res = try!(self.hc.get(url).send());
+    pub fn usage(&self) -> Result<Usage, error::Error> {
+        self.usage_with_meta().map(|res| res.data)
+    }
+
+    /// Like `usage`, along with the metadata of the response.
+    pub fn usage_with_meta(&self) -> Result<Response<Usage>, error::Error> {
+        let url = &self.url("usage.json", false);
+        let res: Response<Usage> = try!(self.get_with_meta(url));
+        if let Some(ref metrics) = self.metrics {
+            metrics.requests_remaining(res.data.data.usage.requests_remaining);
+        }
+        Ok(res)
+    }
+
+    /// Build the URL of `endpoint`, optionally asking for the configured base currency and
+    /// symbols.
+    fn url(&self, endpoint: &str, with_rate_params: bool) -> String {
+        let mut url = format!("{}/{}?app_id={}",
+                              self.base_url.trim_right_matches('/'),
+                              endpoint,
+                              self.app_id);
+        if with_rate_params {
+            if let Some(ref base) = self.base {
+                url.push_str("&base=");
+                url.push_str(base);
+            }
+            if let Some(ref symbols) = self.symbols {
+                url.push_str("&symbols=");
+                url.push_str(symbols);
+            }
+        }
+        url
+    }
+
+    fn currencies_url(&self, options: &CurrenciesOptions) -> String {
+        let mut url = self.url("currencies.json", false);
+        if options.show_alternative {
+            url.push_str("&show_alternative=1");
+        }
+        if options.show_inactive {
+            url.push_str("&show_inactive=1");
+        }
+        url
+    }
+
+    /// Wait until the rate limit allows another request.
+    fn throttle(&self) {
+        if let Some(min_interval) = self.min_interval {
+            let mut last_request = self.last_request.lock().unwrap();
+            if let Some(previous) = *last_request {
+                let elapsed = previous.elapsed();
+                if elapsed < min_interval {
+                    thread::sleep(min_interval - elapsed);
+                }
+            }
+            *last_request = Some(Instant::now());
+        }
+    }
+
+    fn get_with_meta<T>(&self, url: &str) -> Result<Response<T>, error::Error>
+        where T: serde::Deserialize
+    {
+        self.get_checked(url, |_: &T| Ok(()))
+    }
+
+    /// Like `get_with_meta`, failing with the error of `check` if it rejects the response. The
+    /// metrics record the final result, including that error.
+    fn get_checked<T, F>(&self, url: &str, check: F) -> Result<Response<T>, error::Error>
+        where T: serde::Deserialize,
+              F: FnOnce(&T) -> Result<(), error::Error>
+    {
+        self.throttle();
+        let start = Instant::now();
+        let mut status = None;
+        let result = self.fetch(url, start, &mut status)
+            .and_then(|res| check(&res.data).map(|()| res));
+        if let Some(ref metrics) = self.metrics {
+            metrics.observe(endpoint(&self.base_url, url),
+                            status,
+                            start.elapsed(),
+                            result.as_ref().err());
+        }
+        result
+    }
 
-        let mut body = String::new();
-        try!(res.read_to_string(&mut body));
+    /// Send the request and decode the response, keeping its status even if decoding fails.
+    fn fetch<T>(&self,
+                url: &str,
+                start: Instant,
+                status: &mut Option<u16>)
+                -> Result<Response<T>, error::Error>
+        where T: serde::Deserialize
+    {
+        let mut res = try!(self.hc.get(url).send());
+        *status = Some(res.status.to_u16());
 
-        let deserialized: Usage = try!(serde_json::from_str(&body));
-        Ok(deserialized)
+        let deserialized: T = try!(serde_json::from_reader(&mut res));
+        let meta = Meta::new(url, res.status.to_u16(), start.elapsed(), &res.headers);
+        Ok(Response {
+            data: deserialized,
+            meta: meta,
+        })
     }
 }
 
+/// The endpoint of `url` to label metrics with, e.g. `historical` for
+/// `<base_url>/historical/2016-04-01.json?app_id=...`.
+fn endpoint<'u>(base_url: &str, url: &'u str) -> &'u str {
+    let path = url.trim_left_matches(base_url.trim_right_matches('/')).trim_left_matches('/');
+    let path = path.split('?').next().unwrap_or(path);
+    path.split('/').next().unwrap_or(path).trim_right_matches(".json")
+}
+
 #[cfg(test)]
 mod tests {
     use std::borrow::Cow;
+    use std::collections::BTreeMap;
+    use std::io::{self, Read, Write};
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
 
+    use chrono;
     use chrono::*;
     use hyper;
+    use serde;
+    use serde_json;
+    use serde_json::Value;
 
+    use metrics::Metrics;
     use super::*;
 
+    /// Compare two JSON values, treating numbers as equal when their `f64` values are, since
+    /// `2` and `2.0` are the same rate on the wire.
+    fn json_eq(a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (&Value::Object(ref a), &Value::Object(ref b)) => {
+                a.len() == b.len() &&
+                a.iter().all(|(k, v)| b.get(k).map_or(false, |w| json_eq(v, w)))
+            }
+            (&Value::Array(ref a), &Value::Array(ref b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(v, w)| json_eq(v, w))
+            }
+            _ => {
+                match (a.as_f64(), b.as_f64()) {
+                    (Some(x), Some(y)) => x == y,
+                    _ => a == b,
+                }
+            }
+        }
+    }
+
+    fn assert_wire_round_trip<T>(wire: &str)
+        where T: serde::Serialize + serde::Deserialize + PartialEq + ::std::fmt::Debug
+    {
+        let decoded: T = serde_json::from_str(wire).unwrap();
+        let encoded = serde_json::to_string(&decoded).unwrap();
+        assert!(json_eq(&serde_json::from_str(&encoded).unwrap(),
+                        &serde_json::from_str(wire).unwrap()),
+                "{} does not match {}",
+                encoded,
+                wire);
+        assert_eq!(serde_json::from_str::<T>(&encoded).unwrap(), decoded);
+    }
+
     #[test]
     fn new_client() {
         let app_id = "1234";
@@ -126,6 +555,49 @@ mod tests {
         Client::new(app_id);
     }
 
+    #[test]
+    fn url_uses_base_url_and_base() {
+        let client = Client::new("1234")
+            .base_url("http://localhost:8080/api/")
+            .base("EUR")
+            .symbols(vec!["MYR", "SGD"]);
+        assert_eq!(client.url("latest.json", true),
+                   "http://localhost:8080/api/latest.json?app_id=1234&base=EUR&symbols=MYR,SGD");
+        assert_eq!(client.url("usage.json", false),
+                   "http://localhost:8080/api/usage.json?app_id=1234");
+    }
+
+    #[test]
+    fn currencies_url_has_options() {
+        let client = Client::new("1234").base("EUR");
+        let mut options = CurrenciesOptions::default();
+        assert_eq!(client.currencies_url(&options),
+                   "https://openexchangerates.org/api/currencies.json?app_id=1234");
+        options.show_inactive = true;
+        assert!(client.currencies_url(&options).ends_with("?app_id=1234&show_inactive=1"));
+        options.show_alternative = true;
+        assert!(client.currencies_url(&options)
+            .ends_with("?app_id=1234&show_alternative=1&show_inactive=1"));
+    }
+
+    fn mock_client<C, S>(connector: C) -> Client<'static>
+        where C: hyper::net::NetworkConnector<Stream = S> + Send + Sync + 'static,
+              S: hyper::net::NetworkStream + Send
+    {
+        Client {
+            app_id: Cow::Borrowed("1234"),
+            base_url: Cow::Borrowed(DEFAULT_BASE_URL),
+            base: None,
+            symbols: None,
+            max_age: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            min_interval: None,
+            last_request: Mutex::new(None),
+            metrics: None,
+            hc: hyper::Client::with_connector(connector),
+        }
+    }
+
     mock_connector!(LatestConnector {
         "https://openexchangerates.org" =>
 r###"HTTP/1.1 200 OK
@@ -322,10 +794,7 @@ Content-Type: application/json; charset=utf-8
 
     #[test]
     fn latest_works() {
-        let client = Client {
-            app_id: Cow::Borrowed("1234"),
-            hc: hyper::Client::with_connector(LatestConnector::default()),
-        };
+        let client = mock_client(LatestConnector::default());
 
         let res = client.latest();
         assert!(res.is_ok());
@@ -336,7 +805,112 @@ Content-Type: application/json; charset=utf-8
         assert!(rate.timestamp != 0);
         assert_eq!(rate.base, "USD");
         assert_eq!(rate.rates.len(), 171);
-        assert_eq!(rate.rates.get("MYR"), Some(&3.917198_f32));
+        assert_eq!(rate.rates.get("MYR"), Some(&3.917198_f64));
+    }
+
+    #[test]
+    fn latest_with_meta_works() {
+        let client = mock_client(LatestConnector::default());
+
+        let res = client.latest_with_meta().unwrap();
+        assert_eq!(res.data.rates.get("MYR"), Some(&3.917198_f64));
+        assert_eq!(res.meta.url,
+                   "https://openexchangerates.org/api/latest.json?app_id=REDACTED");
+        assert_eq!(res.meta.status, 200);
+        assert_eq!(res.meta.date, Some("Tue, 05 Apr 2016 11:34:18 GMT".to_owned()));
+        assert_eq!(res.meta.etag, Some("\"686eef0d2c12ae0108d2310cccb414a6\"".to_owned()));
+        assert_eq!(res.meta.last_modified,
+                   Some("Tue, 05 Apr 2016 11:00:02 GMT".to_owned()));
+        assert_eq!(res.meta.cache_control, Some("public".to_owned()));
+    }
+
+    #[test]
+    fn latest_stale() {
+        let client = mock_client(LatestConnector::default()).max_age(chrono::Duration::hours(1));
+
+        match client.latest() {
+            Err(error::Error::Stale { age }) => assert!(age > chrono::Duration::hours(1)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    mock_connector!(BidAskConnector {
+        "https://openexchangerates.org" =>
+r###"HTTP/1.1 200 OK
+Date: Tue, 05 Apr 2016 11:34:18 GMT
+Server: Apache
+Cache-Control: public
+Access-Control-Allow-Origin: *
+Content-Length: 433
+Connection: close
+Content-Type: application/json; charset=utf-8
+
+{
+  "disclaimer": "Usage subject to terms: https://openexchangerates.org/terms",
+  "license": "https://openexchangerates.org/license",
+  "timestamp": 1459854002,
+  "base": "USD",
+  "rates": {
+    "EUR": {
+      "bid": 0.880353,
+      "ask": 0.880453,
+      "mid": 0.880403
+    },
+    "MYR": {
+      "bid": 3.9161,
+      "ask": 3.9183,
+      "mid": 3.9172
+    },
+    "USD": {
+      "bid": 1,
+      "ask": 1,
+      "mid": 1
+    }
+  }
+}"###
+    });
+
+    #[test]
+    fn latest_bid_ask_works() {
+        let client = mock_client(BidAskConnector::default());
+
+        let rate = client.latest_bid_ask().unwrap();
+        assert_eq!(rate.base, "USD");
+        assert_eq!(rate.rates.len(), 3);
+        assert_eq!(rate.rates["MYR"],
+                   Quote {
+                       bid: 3.9161,
+                       ask: 3.9183,
+                       mid: 3.9172,
+                   });
+        assert_eq!(rate.mid_rates().rates.get("EUR"), Some(&0.880403));
+    }
+
+    #[test]
+    fn latest_bid_ask_stale() {
+        let client = mock_client(BidAskConnector::default()).max_age(chrono::Duration::hours(1));
+
+        match client.latest_bid_ask() {
+            Err(error::Error::Stale { age }) => assert!(age > chrono::Duration::hours(1)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn historical_bid_ask_works() {
+        let client = mock_client(BidAskConnector::default());
+
+        let res = client.historical_bid_ask_with_meta(NaiveDate::from_ymd(2016, 4, 5)).unwrap();
+        assert_eq!(res.meta.url,
+                   "https://openexchangerates.org/api/historical/2016-04-05.json?app_id=REDACTED\
+                    &show_bid_ask=1");
+        assert_eq!(res.data.timestamp, 1459854002);
+        assert_eq!(res.data.rates["EUR"],
+                   Quote {
+                       bid: 0.880353,
+                       ask: 0.880453,
+                       mid: 0.880403,
+                   });
     }
 
     mock_connector!(CurrenciesConnector {
@@ -529,10 +1103,7 @@ Content-Type: application/json; charset=utf-8
 
     #[test]
     fn currencies_works() {
-        let client = Client {
-            app_id: Cow::Borrowed("1234"),
-            hc: hyper::Client::with_connector(CurrenciesConnector::default()),
-        };
+        let client = mock_client(CurrenciesConnector::default());
 
         let res = client.currencies();
         assert!(res.is_ok());
@@ -544,6 +1115,48 @@ Content-Type: application/json; charset=utf-8
                    Some(&"Malaysian Ringgit".to_string()));
     }
 
+    #[test]
+    fn currencies_with_options() {
+        let client = mock_client(CurrenciesConnector::default());
+
+        let options = CurrenciesOptions {
+            show_alternative: true,
+            show_inactive: true,
+        };
+        let currencies = client.currencies_with(&options).unwrap();
+        assert_eq!(currencies.len(), 171);
+        assert!(currencies["MYR"].is_active());
+        assert_eq!(currencies["BTC"].kind(), Some(catalog::CurrencyKind::Alternative));
+        assert_eq!(currencies["ZMK"].kind(), Some(catalog::CurrencyKind::Obsolete));
+    }
+
+    #[test]
+    fn currencies_with_options_meta_works() {
+        let client = mock_client(CurrenciesConnector::default());
+
+        let options = CurrenciesOptions {
+            show_alternative: true,
+            show_inactive: false,
+        };
+        let res = client.currencies_with_options_meta(&options).unwrap();
+        assert_eq!(res.meta.url,
+                   "https://openexchangerates.org/api/currencies.json?app_id=REDACTED\
+                    &show_alternative=1");
+        assert_eq!(res.meta.etag, Some("\"b3eb21df82ca046c024091278c757705\"".to_owned()));
+        assert_eq!(res.data["BTC"].kind(), Some(catalog::CurrencyKind::Alternative));
+    }
+
+    #[test]
+    fn currencies_in_catalog() {
+        let client = mock_client(CurrenciesConnector::default());
+
+        let currencies = catalog::merge(&client.currencies().unwrap());
+        for currency in currencies.values() {
+            assert!(currency.info.is_some(), "{} is not in the catalog", currency.code);
+        }
+        assert_eq!(currencies["EEK"].kind(), Some(catalog::CurrencyKind::Obsolete));
+    }
+
 
     mock_connector!(HistoricalConnector {
         "https://openexchangerates.org" =>
@@ -731,10 +1344,7 @@ Content-Type: application/json; charset=utf-8
 
     #[test]
     fn historical_works() {
-        let client = Client {
-            app_id: Cow::Borrowed("1234"),
-            hc: hyper::Client::with_connector(HistoricalConnector::default()),
-        };
+        let client = mock_client(HistoricalConnector::default());
 
         let res = client.historical(NaiveDate::from_ymd(2013, 2, 16));
         assert!(res.is_ok());
@@ -745,7 +1355,185 @@ Content-Type: application/json; charset=utf-8
         assert!(rate.timestamp != 0);
         assert_eq!(rate.base, "USD");
         assert_eq!(rate.rates.len(), 161);
-        assert_eq!(rate.rates.get("MYR"), Some(&3.094163_f32));
+        assert_eq!(rate.rates.get("MYR"), Some(&3.094163_f64));
+    }
+
+    /// Answers `historical/2013-02-DD.json` with a rate of DD for MYR, except for the 16th which
+    /// is not available.
+    struct DateConnector;
+
+    impl hyper::net::NetworkConnector for DateConnector {
+        type Stream = DateStream;
+
+        fn connect(&self, _: &str, _: u16, _: &str) -> hyper::Result<DateStream> {
+            Ok(DateStream {
+                request: Vec::new(),
+                response: None,
+            })
+        }
+    }
+
+    struct DateStream {
+        request: Vec<u8>,
+        response: Option<io::Cursor<Vec<u8>>>,
+    }
+
+    impl Read for DateStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.response.is_none() {
+                let request = String::from_utf8_lossy(&self.request).into_owned();
+                let start = request.find("/historical/").expect("historical request") + 12;
+                let day: i64 = request[start + 8..start + 10].parse().unwrap();
+                let (status, body) = if day == 16 {
+                    ("400 Bad Request",
+                     r#"{"error": true, "status": 400, "message": "not_available"}"#.to_owned())
+                } else {
+                    ("200 OK",
+                     format!(r#"{{"disclaimer": "", "license": "", "timestamp": {},
+                                  "base": "USD", "rates": {{"MYR": {}}}}}"#,
+                             1360800000 + (day - 14) * 86400,
+                             day))
+                };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: \
+                                        close\r\n\r\n{}",
+                                       status,
+                                       body.len(),
+                                       body);
+                self.response = Some(io::Cursor::new(response.into_bytes()));
+            }
+            self.response.as_mut().unwrap().read(buf)
+        }
+    }
+
+    impl Write for DateStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.request.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl hyper::net::NetworkStream for DateStream {
+        fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+            Ok("127.0.0.1:1337".parse().unwrap())
+        }
+
+        fn set_read_timeout(&self, _: Option<Duration>) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn set_write_timeout(&self, _: Option<Duration>) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn historical_many_works() {
+        let client = mock_client(DateConnector).concurrency(2);
+        let dates: Vec<NaiveDate> = (14..19).map(|day| NaiveDate::from_ymd(2013, 2, day)).collect();
+
+        let results = client.historical_many(&dates);
+        assert_eq!(results.len(), 5);
+        for (date, result) in dates.iter().zip(&results) {
+            if date.day() == 16 {
+                assert!(result.is_err(), "{} must fail", date);
+            } else {
+                let rate = result.as_ref().unwrap();
+                assert_eq!(rate.date(), *date);
+                assert_eq!(rate.rates["MYR"], date.day() as f64);
+            }
+        }
+        assert!(client.historical_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn historical_many_rate_limit() {
+        let client = mock_client(HistoricalConnector::default()).concurrency(3).rate_limit(20);
+        let dates = vec![NaiveDate::from_ymd(2013, 2, 16); 4];
+
+        let start = Instant::now();
+        assert!(client.historical_many(&dates).iter().all(|result| result.is_ok()));
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn diff_historical_and_latest() {
+        let historical = mock_client(HistoricalConnector::default())
+            .historical(NaiveDate::from_ymd(2013, 2, 16))
+            .unwrap();
+        let latest = mock_client(LatestConnector::default()).latest().unwrap();
+
+        let diff = historical.diff(&latest).unwrap();
+        assert!(!diff.rebased);
+        assert_eq!(diff.changes.len(), 161);
+        assert_eq!(diff.added.len(), 10);
+        assert!(diff.added.contains_key("XAU"));
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changes["MYR"].old, 3.094163);
+        assert_eq!(diff.changes["MYR"].new, 3.917198);
+    }
+
+    mock_connector!(TimeSeriesConnector {
+        "https://openexchangerates.org" =>
+r###"HTTP/1.1 200 OK
+Date: Tue, 05 Apr 2016 11:36:02 GMT
+Server: Apache
+Access-Control-Allow-Origin: *
+Content-Length: 409
+Connection: close
+Content-Type: application/json; charset=utf-8
+
+{
+  "disclaimer": "https://openexchangerates.org/terms/",
+  "license": "https://openexchangerates.org/license/",
+  "start_date": "2013-01-01",
+  "end_date": "2013-01-03",
+  "base": "USD",
+  "rates": {
+    "2013-01-01": {
+      "MYR": 3.0593,
+      "SGD": 1.2219
+    },
+    "2013-01-02": {
+      "MYR": 3.0375,
+      "SGD": 1.2203
+    },
+    "2013-01-03": {
+      "MYR": 3.0425,
+      "SGD": 1.2252
+    }
+  }
+}"###
+    });
+
+    #[test]
+    fn time_series_works() {
+        let client = mock_client(TimeSeriesConnector::default());
+
+        let (start, end) = (NaiveDate::from_ymd(2013, 1, 1), NaiveDate::from_ymd(2013, 1, 3));
+        let series = client.time_series(start, end).unwrap();
+        assert_eq!(series.base(), "USD");
+
+        let rates: Vec<ExchangeRate> = series.map(|rate| rate.unwrap()).collect();
+        assert_eq!(rates.len(), 3);
+        assert_eq!(rates[0].date(), NaiveDate::from_ymd(2013, 1, 1));
+        assert_eq!(rates[2].rates.get("SGD"), Some(&1.2252_f64));
+    }
+
+    #[test]
+    fn time_series_with_meta_works() {
+        let client = mock_client(TimeSeriesConnector::default());
+
+        let (start, end) = (NaiveDate::from_ymd(2013, 1, 1), NaiveDate::from_ymd(2013, 1, 3));
+        let res = client.time_series_with_meta(start, end).unwrap();
+        assert_eq!(res.meta.url,
+                   "https://openexchangerates.org/api/time-series.json?app_id=REDACTED\
+                    &start=2013-01-01&end=2013-01-03");
+        assert_eq!(res.meta.status, 200);
+        assert_eq!(res.meta.date, Some("Tue, 05 Apr 2016 11:36:02 GMT".to_owned()));
+        assert_eq!(res.data.count(), 3);
     }
 
     mock_connector!(UsageConnector {
@@ -787,12 +1575,18 @@ Content-Type: application/json; charset=utf-8
 }"###
     });
 
+    #[test]
+    fn latest_lenient_works() {
+        let client = mock_client(LatestConnector::default());
+
+        let lenient = client.latest_lenient().unwrap();
+        assert!(lenient.warnings.is_empty());
+        assert_eq!(lenient.data, client.latest().unwrap());
+    }
+
     #[test]
     fn usage_works() {
-        let client = Client {
-            app_id: Cow::Borrowed("1234"),
-            hc: hyper::Client::with_connector(UsageConnector::default()),
-        };
+        let client = mock_client(UsageConnector::default());
 
         let res = client.usage();
         assert!(res.is_ok());
@@ -814,6 +1608,7 @@ Content-Type: application/json; charset=utf-8
                                    experimental: true,
                                    time_series: false,
                                    convert: false,
+                                   extra: BTreeMap::new(),
                                },
                            },
                            usage: UsageDataUsage {
@@ -827,4 +1622,153 @@ Content-Type: application/json; charset=utf-8
                        },
                    });
     }
+
+    #[test]
+    fn metrics_record_requests() {
+        let metrics = Arc::new(Metrics::new());
+        let client = mock_client(LatestConnector::default()).metrics(metrics.clone());
+        client.latest().unwrap();
+        client.latest().unwrap();
+        let client = mock_client(UsageConnector::default()).metrics(metrics.clone());
+        client.usage().unwrap();
+
+        let out = metrics.render();
+        for line in &["oxr_client_requests_total{endpoint=\"latest\"} 2",
+                      "oxr_client_requests_total{endpoint=\"usage\"} 1",
+                      "oxr_client_responses_total{endpoint=\"latest\",status=\"200\"} 2",
+                      "oxr_client_request_duration_seconds_count{endpoint=\"latest\"} 2",
+                      "oxr_client_requests_remaining 989"] {
+            assert!(out.lines().any(|l| l == *line), "missing `{}` in:\n{}", line, out);
+        }
+        assert!(!out.contains("oxr_client_errors_total{"));
+    }
+
+    #[test]
+    fn metrics_record_stale_rates() {
+        let metrics = Arc::new(Metrics::new());
+        let client = mock_client(LatestConnector::default())
+            .max_age(chrono::Duration::hours(1))
+            .metrics(metrics.clone());
+        assert!(client.latest().is_err());
+
+        let out = metrics.render();
+        for line in &["oxr_client_responses_total{endpoint=\"latest\",status=\"200\"} 1",
+                      "oxr_client_errors_total{endpoint=\"latest\",kind=\"stale\"} 1"] {
+            assert!(out.lines().any(|l| l == *line), "missing `{}` in:\n{}", line, out);
+        }
+    }
+
+    #[test]
+    fn metrics_record_stale_lenient_rates() {
+        let metrics = Arc::new(Metrics::new());
+        let client = mock_client(LatestConnector::default())
+            .max_age(chrono::Duration::hours(1))
+            .metrics(metrics.clone());
+        match client.latest_lenient() {
+            Err(error::Error::Stale { .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let out = metrics.render();
+        for line in &["oxr_client_responses_total{endpoint=\"latest\",status=\"200\"} 1",
+                      "oxr_client_errors_total{endpoint=\"latest\",kind=\"stale\"} 1"] {
+            assert!(out.lines().any(|l| l == *line), "missing `{}` in:\n{}", line, out);
+        }
+    }
+
+    #[test]
+    fn metrics_record_time_series_once_read() {
+        let metrics = Arc::new(Metrics::new());
+        let client = mock_client(TimeSeriesConnector::default()).metrics(metrics.clone());
+
+        let (start, end) = (NaiveDate::from_ymd(2013, 1, 1), NaiveDate::from_ymd(2013, 1, 3));
+        let series = client.time_series(start, end).unwrap();
+        assert!(!metrics.render().contains("oxr_client_requests_total{"));
+        assert_eq!(series.count(), 3);
+
+        let out = metrics.render();
+        for line in &["oxr_client_requests_total{endpoint=\"time-series\"} 1",
+                      "oxr_client_responses_total{endpoint=\"time-series\",status=\"200\"} 1",
+                      "oxr_client_request_duration_seconds_count{endpoint=\"time-series\"} 1"] {
+            assert!(out.lines().any(|l| l == *line), "missing `{}` in:\n{}", line, out);
+        }
+        assert!(!out.contains("oxr_client_errors_total{"));
+    }
+
+    #[test]
+    fn metrics_endpoint_names() {
+        let base_url = DEFAULT_BASE_URL;
+        let url = format!("{}/historical/2016-04-01.json?app_id=1234", base_url);
+        assert_eq!(super::endpoint(base_url, &url), "historical");
+        let url = format!("{}/latest.json?app_id=1234&show_bid_ask=1", base_url);
+        assert_eq!(super::endpoint(base_url, &url), "latest");
+        assert_eq!(super::endpoint("http://localhost:8080/api/",
+                                   "http://localhost:8080/api/currencies.json?app_id=1234"),
+                   "currencies");
+    }
+
+    #[test]
+    fn latest_round_trip() {
+        let client = mock_client(LatestConnector::default());
+        let rate = client.latest().unwrap();
+
+        let encoded = serde_json::to_string(&rate).unwrap();
+        let decoded: ExchangeRate = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded, rate);
+    }
+
+    #[test]
+    fn exchange_rate_wire_format() {
+        assert_wire_round_trip::<ExchangeRate>(r#"{
+            "disclaimer": "Usage subject to terms: https://openexchangerates.org/terms",
+            "license": "https://openexchangerates.org/license",
+            "timestamp": 1459854002,
+            "base": "USD",
+            "rates": {
+                "BBD": 2,
+                "BTC": 0.0023746257,
+                "MYR": 3.917198,
+                "VND": 22306.566667
+            }
+        }"#);
+    }
+
+    #[test]
+    fn timestamp_out_of_range() {
+        let json = r#"{"disclaimer": "", "license": "", "timestamp": 9223372036854775807,
+                       "base": "USD", "rates": {}}"#;
+        assert!(serde_json::from_str::<ExchangeRate>(json).is_err());
+        assert!(serde_json::from_str::<BidAskRate>(json).is_err());
+    }
+
+    #[test]
+    fn usage_wire_format() {
+        assert_wire_round_trip::<Usage>(r#"{
+            "status": 200,
+            "data": {
+                "app_id": "1234",
+                "status": "active",
+                "plan": {
+                    "name": "Forever Free",
+                    "quota": "1,000 requests/month",
+                    "update_frequency": "hourly",
+                    "features": {
+                        "base": false,
+                        "symbols": false,
+                        "experimental": true,
+                        "time-series": false,
+                        "convert": false
+                    }
+                },
+                "usage": {
+                    "requests": 11,
+                    "requests_quota": 1000,
+                    "requests_remaining": 989,
+                    "days_elapsed": 10,
+                    "days_remaining": 20,
+                    "daily_average": 1
+                }
+            }
+        }"#);
+    }
 }
@@ -0,0 +1,287 @@
+//! Metrics module
+//!
+//! Counts the requests of a `Client` and the lookups of a `CachingProvider`, for export in the
+//! Prometheus text format. Instrumentation is off unless a `Metrics` is given:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! use openexchangerates::Client;
+//! use openexchangerates::metrics::Metrics;
+//! use openexchangerates::provider::{CachingProvider, RateProvider};
+//!
+//! let metrics = Arc::new(Metrics::new());
+//! let client = Client::new("YOUR_APP_ID").metrics(metrics.clone());
+//! let provider = CachingProvider::new(client, Duration::from_secs(3600)).metrics(metrics.clone());
+//! provider.latest().unwrap();
+//! print!("{}", metrics.render());
+//! ```
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use error::Error;
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+pub const LATENCY_BUCKETS: &'static [f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Counters of requests, responses, errors, latencies and cache lookups, labelled by endpoint
+/// such as `latest` or `historical`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    requests: BTreeMap<String, u64>,
+    responses: BTreeMap<(String, u16), u64>,
+    errors: BTreeMap<(String, &'static str), u64>,
+    latencies: BTreeMap<String, Histogram>,
+    cache: BTreeMap<(String, &'static str), u64>,
+    requests_remaining: Option<i64>,
+}
+
+#[derive(Debug)]
+struct Histogram {
+    /// Observations in each of `LATENCY_BUCKETS`, not cumulative.
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new() -> Histogram {
+        Histogram {
+            buckets: vec![0; LATENCY_BUCKETS.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, seconds: f64) {
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+impl Metrics {
+    /// Create empty metrics.
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Record a request to `endpoint`, with the HTTP status if a response was received and the
+    /// error if it failed.
+    pub fn observe(&self,
+                   endpoint: &str,
+                   status: Option<u16>,
+                   latency: Duration,
+                   error: Option<&Error>) {
+        let mut state = self.state.lock().unwrap();
+        *state.requests.entry(endpoint.to_owned()).or_insert(0) += 1;
+        if let Some(status) = status {
+            *state.responses.entry((endpoint.to_owned(), status)).or_insert(0) += 1;
+        }
+        if let Some(error) = error {
+            *state.errors.entry((endpoint.to_owned(), error.kind())).or_insert(0) += 1;
+        }
+        state.latencies
+            .entry(endpoint.to_owned())
+            .or_insert_with(Histogram::new)
+            .observe(seconds(latency));
+    }
+
+    /// Record a cache lookup for `endpoint`.
+    pub fn cache(&self, endpoint: &str, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        let mut state = self.state.lock().unwrap();
+        *state.cache.entry((endpoint.to_owned(), result)).or_insert(0) += 1;
+    }
+
+    /// The cache hits and misses recorded for `endpoint`.
+    pub fn cache_lookups(&self, endpoint: &str) -> (u64, u64) {
+        let state = self.state.lock().unwrap();
+        let count = |result: &'static str| {
+            state.cache.get(&(endpoint.to_owned(), result)).cloned().unwrap_or(0)
+        };
+        (count("hit"), count("miss"))
+    }
+
+    /// Record the `requests_remaining` of a usage response.
+    pub fn requests_remaining(&self, remaining: i64) {
+        self.state.lock().unwrap().requests_remaining = Some(remaining);
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut out = String::new();
+
+        header(&mut out,
+               "oxr_client_requests_total",
+               "counter",
+               "Requests sent to the API.");
+        for (endpoint, count) in &state.requests {
+            out.push_str(&format!("oxr_client_requests_total{{endpoint=\"{}\"}} {}\n",
+                                  endpoint,
+                                  count));
+        }
+
+        header(&mut out,
+               "oxr_client_responses_total",
+               "counter",
+               "Responses received, by HTTP status.");
+        for (&(ref endpoint, status), count) in &state.responses {
+            out.push_str(&format!("oxr_client_responses_total{{endpoint=\"{}\",status=\"{}\"}} \
+                                   {}\n",
+                                  endpoint,
+                                  status,
+                                  count));
+        }
+
+        header(&mut out,
+               "oxr_client_errors_total",
+               "counter",
+               "Failed requests, by error kind.");
+        for (&(ref endpoint, kind), count) in &state.errors {
+            out.push_str(&format!("oxr_client_errors_total{{endpoint=\"{}\",kind=\"{}\"}} {}\n",
+                                  endpoint,
+                                  kind,
+                                  count));
+        }
+
+        header(&mut out,
+               "oxr_client_request_duration_seconds",
+               "histogram",
+               "Time from sending a request to reading the response.");
+        for (endpoint, histogram) in &state.latencies {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += *count;
+                out.push_str(&format!("oxr_client_request_duration_seconds_bucket{{endpoint=\
+                                       \"{}\",le=\"{}\"}} {}\n",
+                                      endpoint,
+                                      bound,
+                                      cumulative));
+            }
+            out.push_str(&format!("oxr_client_request_duration_seconds_bucket{{endpoint=\"{}\",\
+                                   le=\"+Inf\"}} {}\n\
+                                   oxr_client_request_duration_seconds_sum{{endpoint=\"{}\"}} \
+                                   {}\n\
+                                   oxr_client_request_duration_seconds_count{{endpoint=\"{}\"}} \
+                                   {}\n",
+                                  endpoint,
+                                  histogram.count,
+                                  endpoint,
+                                  histogram.sum,
+                                  endpoint,
+                                  histogram.count));
+        }
+
+        header(&mut out,
+               "oxr_client_cache_lookups_total",
+               "counter",
+               "Cache lookups, by result.");
+        for (&(ref endpoint, result), count) in &state.cache {
+            out.push_str(&format!("oxr_client_cache_lookups_total{{endpoint=\"{}\",result=\"{}\"}} \
+                                   {}\n",
+                                  endpoint,
+                                  result,
+                                  count));
+        }
+
+        if let Some(remaining) = state.requests_remaining {
+            header(&mut out,
+                   "oxr_client_requests_remaining",
+                   "gauge",
+                   "Requests left in the current period, as last reported by the usage endpoint.");
+            out.push_str(&format!("oxr_client_requests_remaining {}\n", remaining));
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use error::Error;
+    use super::*;
+
+    #[test]
+    fn renders_counters_and_histograms() {
+        let metrics = Metrics::new();
+        metrics.observe("latest", Some(200), Duration::from_millis(80), None);
+        metrics.observe("latest", Some(200), Duration::from_millis(300), None);
+        metrics.observe("latest",
+                        Some(401),
+                        Duration::from_millis(20),
+                        Some(&Error::Decode("invalid_app_id".to_owned())));
+        metrics.observe("historical",
+                        None,
+                        Duration::from_secs(30),
+                        Some(&Error::Unavailable("timeout".to_owned())));
+        metrics.cache("latest", true);
+        metrics.cache("latest", false);
+        metrics.cache("latest", true);
+        assert_eq!(metrics.cache_lookups("latest"), (2, 1));
+        assert_eq!(metrics.cache_lookups("historical"), (0, 0));
+
+        let out = metrics.render();
+        let bucket = |endpoint: &str, le: &str, count: u64| {
+            format!("oxr_client_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}",
+                    endpoint,
+                    le,
+                    count)
+        };
+        let lines = vec!["# TYPE oxr_client_requests_total counter".to_owned(),
+                         "oxr_client_requests_total{endpoint=\"latest\"} 3".to_owned(),
+                         "oxr_client_requests_total{endpoint=\"historical\"} 1".to_owned(),
+                         "oxr_client_responses_total{endpoint=\"latest\",status=\"200\"} 2"
+                             .to_owned(),
+                         "oxr_client_responses_total{endpoint=\"latest\",status=\"401\"} 1"
+                             .to_owned(),
+                         "oxr_client_errors_total{endpoint=\"latest\",kind=\"decode\"} 1"
+                             .to_owned(),
+                         "oxr_client_errors_total{endpoint=\"historical\",kind=\"unavailable\"} 1"
+                             .to_owned(),
+                         bucket("latest", "0.05", 1),
+                         bucket("latest", "0.1", 2),
+                         bucket("latest", "0.5", 3),
+                         bucket("historical", "10", 0),
+                         bucket("historical", "+Inf", 1),
+                         "oxr_client_request_duration_seconds_count{endpoint=\"latest\"} 3"
+                             .to_owned(),
+                         "oxr_client_request_duration_seconds_sum{endpoint=\"historical\"} 30"
+                             .to_owned(),
+                         "oxr_client_cache_lookups_total{endpoint=\"latest\",result=\"hit\"} 2"
+                             .to_owned(),
+                         "oxr_client_cache_lookups_total{endpoint=\"latest\",result=\"miss\"} 1"
+                             .to_owned()];
+        for line in &lines {
+            assert!(out.lines().any(|l| l == line), "missing `{}` in:\n{}", line, out);
+        }
+        assert!(!out.contains("oxr_client_requests_remaining"));
+    }
+
+    #[test]
+    fn keeps_last_requests_remaining() {
+        let metrics = Metrics::new();
+        metrics.requests_remaining(120);
+        metrics.requests_remaining(119);
+        assert!(metrics.render().ends_with("oxr_client_requests_remaining 119\n"));
+    }
+}
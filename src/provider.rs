@@ -0,0 +1,475 @@
+//! Provider module
+//!
+//! A `RateProvider` is a source of exchange rates. `Client` is one; `CachingProvider` wraps
+//! another provider, `StaticProvider` serves fixed snapshots and `CsvProvider` serves the
+//! snapshots stored in a CSV file. Code written against the trait can swap sources and stack
+//! wrappers:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use openexchangerates::Client;
+//! use openexchangerates::provider::{CachingProvider, RateProvider};
+//!
+//! fn price<P: RateProvider>(provider: &P, amount: f64) -> Option<f64> {
+//!     provider.latest().ok().and_then(|rate| rate.convert(amount, "USD", "MYR"))
+//! }
+//!
+//! let provider = CachingProvider::new(Client::new("YOUR_APP_ID"), Duration::from_secs(3600));
+//! println!("{:?}", price(&provider, 100.0));
+//! ```
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+
+use {CacheConfig, Client, Currencies, ExchangeRate};
+use csv;
+use error::Error;
+use metrics::Metrics;
+
+/// A source of exchange rates.
+pub trait RateProvider {
+    /// The latest exchange rates.
+    fn latest(&self) -> Result<ExchangeRate, Error>;
+
+    /// The exchange rates at the end of `date`.
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error>;
+
+    /// The supported currencies.
+    fn currencies(&self) -> Result<Currencies, Error>;
+}
+
+impl<'a> RateProvider for Client<'a> {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        Client::latest(self)
+    }
+
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        Client::historical(self, date)
+    }
+
+    fn currencies(&self) -> Result<Currencies, Error> {
+        Client::currencies(self)
+    }
+}
+
+impl<'a, P: RateProvider + ?Sized> RateProvider for &'a P {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        (**self).latest()
+    }
+
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        (**self).historical(date)
+    }
+
+    fn currencies(&self) -> Result<Currencies, Error> {
+        (**self).currencies()
+    }
+}
+
+impl<P: RateProvider + ?Sized> RateProvider for Box<P> {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        (**self).latest()
+    }
+
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        (**self).historical(date)
+    }
+
+    fn currencies(&self) -> Result<Currencies, Error> {
+        (**self).currencies()
+    }
+}
+
+/// Serves fixed snapshots and currencies.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaticProvider {
+    rates: Vec<ExchangeRate>,
+    currencies: Currencies,
+}
+
+impl StaticProvider {
+    /// Serve `rates`: the most recent one as latest, and each one for its date.
+    pub fn new(mut rates: Vec<ExchangeRate>) -> StaticProvider {
+        rates.sort_by_key(|rate| rate.timestamp);
+        StaticProvider {
+            rates: rates,
+            currencies: Currencies::new(),
+        }
+    }
+
+    /// Serve `currencies` instead of an empty list.
+    pub fn with_currencies(mut self, currencies: Currencies) -> StaticProvider {
+        self.currencies = currencies;
+        self
+    }
+}
+
+impl RateProvider for StaticProvider {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        self.rates
+            .last()
+            .cloned()
+            .ok_or_else(|| Error::Unavailable("no static exchange rates".to_owned()))
+    }
+
+    /// The latest snapshot of `date`, or `Error::Gap` if there is none.
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        self.rates
+            .iter()
+            .rev()
+            .find(|rate| rate.date() == date)
+            .cloned()
+            .ok_or(Error::Gap(date))
+    }
+
+    fn currencies(&self) -> Result<Currencies, Error> {
+        Ok(self.currencies.clone())
+    }
+}
+
+/// Serves the snapshots stored in a CSV file in the wide layout of the `csv` module, e.g.
+/// appended to by a scheduled job.
+///
+/// The file is read again on every request, so that updates are served at once; wrap the
+/// provider in a `CachingProvider` to read it less often.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvProvider {
+    path: PathBuf,
+    currencies: Currencies,
+}
+
+impl CsvProvider {
+    /// Serve the snapshots in the file at `path`.
+    pub fn new<P>(path: P) -> CsvProvider
+        where P: AsRef<Path>
+    {
+        CsvProvider {
+            path: path.as_ref().to_owned(),
+            currencies: Currencies::new(),
+        }
+    }
+
+    /// Serve `currencies` instead of an empty list, since the file only stores rates.
+    pub fn with_currencies(mut self, currencies: Currencies) -> CsvProvider {
+        self.currencies = currencies;
+        self
+    }
+
+    /// The location of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The snapshots currently in the file.
+    fn load(&self) -> Result<StaticProvider, Error> {
+        let file = try!(File::open(&self.path));
+        Ok(StaticProvider::new(try!(csv::read_wide(file))))
+    }
+}
+
+impl RateProvider for CsvProvider {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        try!(self.load()).latest()
+    }
+
+    /// The latest snapshot of `date` in the file, or `Error::Gap` if there is none.
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        try!(self.load()).historical(date)
+    }
+
+    fn currencies(&self) -> Result<Currencies, Error> {
+        Ok(self.currencies.clone())
+    }
+}
+
+/// Keeps the responses of another provider.
+///
+/// The latest rates and the currencies are kept for the time-to-live. Historical rates do not
+/// change, so they are kept until evicted by newer ones when the capacity is reached. Errors
+/// are not cached.
+pub struct CachingProvider<P> {
+    inner: P,
+    ttl: Duration,
+    capacity: Option<usize>,
+    latest: Mutex<Option<(Instant, ExchangeRate)>>,
+    currencies: Mutex<Option<(Instant, Currencies)>>,
+    historical: Mutex<(BTreeMap<NaiveDate, ExchangeRate>, VecDeque<NaiveDate>)>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl<P: RateProvider> CachingProvider<P> {
+    /// Cache the responses of `inner` for `ttl`, without limiting the historical rates kept.
+    pub fn new(inner: P, ttl: Duration) -> CachingProvider<P> {
+        CachingProvider {
+            inner: inner,
+            ttl: ttl,
+            capacity: None,
+            latest: Mutex::new(None),
+            currencies: Mutex::new(None),
+            historical: Mutex::new((BTreeMap::new(), VecDeque::new())),
+            metrics: None,
+        }
+    }
+
+    /// Cache the responses of `inner` with the settings in `config`.
+    pub fn from_config(inner: P, config: &CacheConfig) -> CachingProvider<P> {
+        let provider = CachingProvider::new(inner, Duration::from_secs(config.ttl));
+        match config.capacity {
+            Some(capacity) => provider.capacity(capacity),
+            None => provider,
+        }
+    }
+
+    /// Keep the historical rates of at most `capacity` dates.
+    pub fn capacity(mut self, capacity: usize) -> CachingProvider<P> {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Record every lookup as a hit or a miss in `metrics`.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> CachingProvider<P> {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The wrapped provider.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Drop every cached response.
+    pub fn clear(&self) {
+        *self.latest.lock().unwrap() = None;
+        *self.currencies.lock().unwrap() = None;
+        let mut historical = self.historical.lock().unwrap();
+        historical.0.clear();
+        historical.1.clear();
+    }
+
+    fn record(&self, endpoint: &str, hit: bool) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.cache(endpoint, hit);
+        }
+    }
+}
+
+impl<P: RateProvider> RateProvider for CachingProvider<P> {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        let mut hit = true;
+        let result = cached(&self.latest, self.ttl, || {
+            hit = false;
+            self.inner.latest()
+        });
+        self.record("latest", hit);
+        result
+    }
+
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        if let Some(rate) = self.historical.lock().unwrap().0.get(&date) {
+            self.record("historical", true);
+            return Ok(rate.clone());
+        }
+
+        self.record("historical", false);
+        let rate = try!(self.inner.historical(date));
+        let mut historical = self.historical.lock().unwrap();
+        let (ref mut rates, ref mut order) = *historical;
+        if rates.insert(date, rate.clone()).is_none() {
+            order.push_back(date);
+        }
+        if let Some(capacity) = self.capacity {
+            while order.len() > capacity {
+                if let Some(oldest) = order.pop_front() {
+                    rates.remove(&oldest);
+                }
+            }
+        }
+        Ok(rate)
+    }
+
+    fn currencies(&self) -> Result<Currencies, Error> {
+        let mut hit = true;
+        let result = cached(&self.currencies, self.ttl, || {
+            hit = false;
+            self.inner.currencies()
+        });
+        self.record("currencies", hit);
+        result
+    }
+}
+
+/// The value in `slot` if it is younger than `ttl`, or else the result of `fetch`, which is
+/// stored on success.
+fn cached<T, F>(slot: &Mutex<Option<(Instant, T)>>, ttl: Duration, fetch: F) -> Result<T, Error>
+    where T: Clone,
+          F: FnOnce() -> Result<T, Error>
+{
+    if let Some((ref fetched, ref value)) = *slot.lock().unwrap() {
+        if fetched.elapsed() < ttl {
+            return Ok(value.clone());
+        }
+    }
+
+    let value = try!(fetch());
+    *slot.lock().unwrap() = Some((Instant::now(), value.clone()));
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::env;
+    use std::fs::{self, File};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use chrono::NaiveDate;
+
+    use {Currencies, ExchangeRate};
+    use csv;
+    use error::Error;
+    use metrics::Metrics;
+    use super::*;
+
+    fn rate(timestamp: i64) -> ExchangeRate {
+        ExchangeRate::fixture(timestamp, "USD", &[("MYR", 3.9)])
+    }
+
+    /// Counts the requests it serves.
+    struct Counting {
+        requests: Cell<usize>,
+    }
+
+    impl RateProvider for Counting {
+        fn latest(&self) -> Result<ExchangeRate, Error> {
+            self.requests.set(self.requests.get() + 1);
+            Ok(rate(1459854002))
+        }
+
+        fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+            self.requests.set(self.requests.get() + 1);
+            Ok(rate(date.and_hms(23, 0, 0).timestamp()))
+        }
+
+        fn currencies(&self) -> Result<Currencies, Error> {
+            self.requests.set(self.requests.get() + 1);
+            Err(Error::Unavailable("offline".to_owned()))
+        }
+    }
+
+    fn counting() -> Counting {
+        Counting { requests: Cell::new(0) }
+    }
+
+    #[test]
+    fn static_provider() {
+        let provider = StaticProvider::new(vec![rate(1459854002), rate(1361055600)]);
+        assert_eq!(provider.latest().unwrap().timestamp, 1459854002);
+        assert_eq!(provider.historical(NaiveDate::from_ymd(2013, 2, 16)).unwrap().timestamp,
+                   1361055600);
+        match provider.historical(NaiveDate::from_ymd(2013, 2, 17)) {
+            Err(Error::Gap(date)) => assert_eq!(date, NaiveDate::from_ymd(2013, 2, 17)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(StaticProvider::new(Vec::new()).latest().is_err());
+    }
+
+    #[test]
+    fn csv_provider_reads_the_file_on_every_request() {
+        let path = env::temp_dir().join("openexchangerates-csv-provider.csv");
+        let provider = CsvProvider::new(&path);
+        match provider.latest() {
+            Err(Error::Io(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        csv::write_wide(File::create(&path).unwrap(), &[rate(1361055600)]).unwrap();
+        assert_eq!(provider.latest().unwrap().timestamp, 1361055600);
+
+        csv::write_wide(File::create(&path).unwrap(), &[rate(1361055600), rate(1459854002)])
+            .unwrap();
+        let latest = provider.latest();
+        let historical = provider.historical(NaiveDate::from_ymd(2013, 2, 16));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(latest.unwrap().timestamp, 1459854002);
+        assert_eq!(historical.unwrap().timestamp, 1361055600);
+        assert!(provider.currencies().unwrap().is_empty());
+    }
+
+    #[test]
+    fn caches_latest_until_ttl() {
+        let provider = CachingProvider::new(counting(), Duration::from_secs(3600));
+        provider.latest().unwrap();
+        provider.latest().unwrap();
+        assert_eq!(provider.inner().requests.get(), 1);
+
+        provider.clear();
+        provider.latest().unwrap();
+        assert_eq!(provider.inner().requests.get(), 2);
+
+        let provider = CachingProvider::new(counting(), Duration::from_secs(0));
+        provider.latest().unwrap();
+        provider.latest().unwrap();
+        assert_eq!(provider.inner().requests.get(), 2);
+    }
+
+    #[test]
+    fn does_not_cache_errors() {
+        let provider = CachingProvider::new(counting(), Duration::from_secs(3600));
+        assert!(provider.currencies().is_err());
+        assert!(provider.currencies().is_err());
+        assert_eq!(provider.inner().requests.get(), 2);
+    }
+
+    #[test]
+    fn evicts_oldest_historical() {
+        let provider = CachingProvider::new(counting(), Duration::from_secs(3600)).capacity(2);
+        let dates: Vec<NaiveDate> = (1..4).map(|day| NaiveDate::from_ymd(2016, 4, day)).collect();
+        for date in &dates {
+            provider.historical(*date).unwrap();
+        }
+        provider.historical(dates[2]).unwrap();
+        assert_eq!(provider.inner().requests.get(), 3);
+        provider.historical(dates[0]).unwrap();
+        assert_eq!(provider.inner().requests.get(), 4);
+    }
+
+    #[test]
+    fn records_hits_and_misses() {
+        let metrics = Arc::new(Metrics::new());
+        let provider = CachingProvider::new(counting(), Duration::from_secs(3600))
+            .metrics(metrics.clone());
+        provider.latest().unwrap();
+        provider.latest().unwrap();
+        provider.historical(NaiveDate::from_ymd(2016, 4, 1)).unwrap();
+        assert!(provider.currencies().is_err());
+
+        let out = metrics.render();
+        let lookups = |endpoint: &str, result: &str, count: u64| {
+            format!("oxr_client_cache_lookups_total{{endpoint=\"{}\",result=\"{}\"}} {}\n",
+                    endpoint,
+                    result,
+                    count)
+        };
+        assert!(out.contains(&lookups("latest", "hit", 1)));
+        assert!(out.contains(&lookups("latest", "miss", 1)));
+        assert!(out.contains(&lookups("historical", "miss", 1)));
+        assert!(out.contains(&lookups("currencies", "miss", 1)));
+    }
+
+    #[test]
+    fn stacks_through_references() {
+        let provider = StaticProvider::new(vec![rate(1459854002)]);
+        let cached = CachingProvider::new(&provider, Duration::from_secs(60));
+        assert_eq!(cached.latest().unwrap().timestamp, 1459854002);
+
+        let boxed: Box<RateProvider> = Box::new(provider.clone());
+        let cached = CachingProvider::new(boxed, Duration::from_secs(60));
+        assert_eq!(cached.latest().unwrap().timestamp, 1459854002);
+    }
+}
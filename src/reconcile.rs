@@ -0,0 +1,228 @@
+//! Reconcile module
+//!
+//! Checks the exchange rates of one source against another for the same date, e.g.
+//! OpenExchangeRates against the ECB reference rates:
+//!
+//! ```ignore
+//! let ours = client.historical(date).unwrap();
+//! let theirs = ecb::parse(&xml).unwrap().pop().unwrap();
+//! let report = reconcile::reconcile(&ours, &theirs, 0.5).unwrap();
+//! print!("{}", report);
+//! ```
+use std::cmp::Ordering;
+use std::fmt;
+
+use chrono::NaiveDate;
+
+use ExchangeRate;
+use error::Error;
+
+/// A currency whose rates differ by more than the tolerance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deviation {
+    pub currency: String,
+    pub ours: f64,
+    pub theirs: f64,
+    /// Relative difference from `ours` to `theirs`, in percent.
+    pub percent: f64,
+}
+
+/// Summary statistics of the absolute relative differences, in percent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+    /// Number of currencies both sources have, besides the base currency.
+    pub compared: usize,
+    /// Number of compared currencies within the tolerance.
+    pub within_tolerance: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+/// The result of `reconcile`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reconciliation {
+    /// The base currency both snapshots are compared in, that of `ours`.
+    pub base: String,
+    pub our_date: NaiveDate,
+    pub their_date: NaiveDate,
+    /// Maximum relative difference tolerated, in percent.
+    pub tolerance: f64,
+    /// Currencies beyond the tolerance, the largest difference first.
+    pub deviations: Vec<Deviation>,
+    /// Currencies only `ours` has.
+    pub only_ours: Vec<String>,
+    /// Currencies only `theirs` has.
+    pub only_theirs: Vec<String>,
+    pub summary: Summary,
+}
+
+impl Reconciliation {
+    /// Whether both sources agree: same date and currencies, and no deviation.
+    pub fn is_clean(&self) -> bool {
+        self.our_date == self.their_date && self.deviations.is_empty() &&
+        self.only_ours.is_empty() && self.only_theirs.is_empty()
+    }
+}
+
+/// Compare `ours` with `theirs`, reporting the currencies that differ by more than `tolerance`
+/// percent.
+///
+/// `theirs` is rebased onto the base currency of `ours` if needed, which fails if it has no
+/// rate for that currency. Snapshots of different dates are compared anyway; check
+/// `our_date` and `their_date`.
+pub fn reconcile(ours: &ExchangeRate,
+                 theirs: &ExchangeRate,
+                 tolerance: f64)
+                 -> Result<Reconciliation, Error> {
+    let diff = try!(ours.diff(theirs));
+
+    let mut differences = Vec::new();
+    let mut deviations = Vec::new();
+    for (currency, change) in &diff.changes {
+        if *currency == diff.base {
+            continue;
+        }
+        let percent = change.percent();
+        differences.push(percent.abs());
+        if percent.abs() > tolerance {
+            deviations.push(Deviation {
+                currency: currency.clone(),
+                ours: change.old,
+                theirs: change.new,
+                percent: percent,
+            });
+        }
+    }
+    deviations.sort_by(|a, b| {
+        b.percent.abs().partial_cmp(&a.percent.abs()).unwrap_or(Ordering::Equal)
+    });
+
+    Ok(Reconciliation {
+        base: diff.base.clone(),
+        our_date: ours.date(),
+        their_date: theirs.date(),
+        tolerance: tolerance,
+        summary: summarize(&mut differences, deviations.len()),
+        deviations: deviations,
+        only_ours: diff.removed.keys().cloned().collect(),
+        only_theirs: diff.added.keys().cloned().collect(),
+    })
+}
+
+fn summarize(differences: &mut Vec<f64>, deviations: usize) -> Summary {
+    differences.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let compared = differences.len();
+    let median = match compared {
+        0 => 0.0,
+        n if n % 2 == 1 => differences[n / 2],
+        n => (differences[n / 2 - 1] + differences[n / 2]) / 2.0,
+    };
+    Summary {
+        compared: compared,
+        within_tolerance: compared - deviations,
+        mean: if compared == 0 {
+            0.0
+        } else {
+            differences.iter().sum::<f64>() / compared as f64
+        },
+        median: median,
+        max: differences.last().cloned().unwrap_or(0.0),
+    }
+}
+
+impl fmt::Display for Reconciliation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(f, "Base: {}", self.base));
+        if self.our_date == self.their_date {
+            try!(writeln!(f, "Date: {}", self.our_date));
+        } else {
+            try!(writeln!(f, "Dates: {} / {}", self.our_date, self.their_date));
+        }
+        try!(writeln!(f,
+                      "Compared: {}, within {}%: {}",
+                      self.summary.compared,
+                      self.tolerance,
+                      self.summary.within_tolerance));
+        try!(writeln!(f,
+                      "Difference: mean {:.4}%, median {:.4}%, max {:.4}%",
+                      self.summary.mean,
+                      self.summary.median,
+                      self.summary.max));
+        for deviation in &self.deviations {
+            try!(writeln!(f,
+                          "{:<6}{:>16}{:>16}{:>+10.4}%",
+                          deviation.currency,
+                          deviation.ours,
+                          deviation.theirs,
+                          deviation.percent));
+        }
+        if !self.only_ours.is_empty() {
+            try!(writeln!(f, "Only ours: {}", self.only_ours.join(", ")));
+        }
+        if !self.only_theirs.is_empty() {
+            try!(writeln!(f, "Only theirs: {}", self.only_theirs.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ExchangeRate;
+    use super::*;
+
+    #[test]
+    fn reports_deviations_and_missing() {
+        let ours = ExchangeRate::fixture(1459854002,
+                                         "USD",
+                                         &[("USD", 1.0), ("EUR", 0.88), ("MYR", 3.9),
+                                           ("SGD", 1.35), ("BTC", 0.0024)]);
+        let theirs = ExchangeRate::fixture(1459814400,
+                                           "EUR",
+                                           &[("EUR", 1.0), ("USD", 1.0 / 0.88),
+                                             ("MYR", 3.9 * 1.01 / 0.88),
+                                             ("SGD", 1.35 * 0.998 / 0.88), ("CNY", 7.37)]);
+
+        let report = reconcile(&ours, &theirs, 0.5).unwrap();
+        assert_eq!(report.base, "USD");
+        assert_eq!(report.our_date, report.their_date);
+        assert_eq!(report.deviations.len(), 1);
+        assert_eq!(report.deviations[0].currency, "MYR");
+        assert!((report.deviations[0].percent - 1.0).abs() < 1e-9);
+        assert_eq!(report.only_ours, vec!["BTC".to_owned()]);
+        assert_eq!(report.only_theirs, vec!["CNY".to_owned()]);
+
+        assert_eq!(report.summary.compared, 3);
+        assert_eq!(report.summary.within_tolerance, 2);
+        assert!((report.summary.median - 0.2).abs() < 1e-9);
+        assert!((report.summary.max - 1.0).abs() < 1e-9);
+        assert!((report.summary.mean - 0.4).abs() < 1e-9);
+        assert!(!report.is_clean());
+        assert!(report.to_string().contains("Only theirs: CNY"));
+    }
+
+    #[test]
+    fn clean_when_identical() {
+        let ours = ExchangeRate::fixture(1459854002, "USD", &[("EUR", 0.88), ("MYR", 3.9)]);
+        let report = reconcile(&ours, &ours, 0.0).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.summary.max, 0.0);
+    }
+
+    #[test]
+    fn clean_when_identical_with_zero_rates() {
+        let ours = ExchangeRate::fixture(1459854002, "USD", &[("EUR", 0.88), ("VEF", 0.0)]);
+        let report = reconcile(&ours, &ours, 0.0).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.summary.mean, 0.0);
+        assert_eq!(report.summary.max, 0.0);
+    }
+
+    #[test]
+    fn fails_without_common_base() {
+        let ours = ExchangeRate::fixture(1459854002, "USD", &[("MYR", 3.9)]);
+        let theirs = ExchangeRate::fixture(1459854002, "EUR", &[("MYR", 4.4)]);
+        assert!(reconcile(&ours, &theirs, 0.5).is_err());
+    }
+}
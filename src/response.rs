@@ -0,0 +1,111 @@
+//! Response module
+//!
+//! The `*_with_meta` endpoints of `Client` return the decoded payload along with details of
+//! the HTTP exchange, for debugging and for conditional requests.
+use std::time::Duration;
+
+use hyper::header::Headers;
+
+/// A decoded payload and the metadata of the response it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response<T> {
+    pub data: T,
+    pub meta: Meta,
+}
+
+impl<T> Response<T> {
+    /// Transform the payload, keeping the metadata.
+    pub fn map<U, F>(self, f: F) -> Response<U>
+        where F: FnOnce(T) -> U
+    {
+        Response {
+            data: f(self.data),
+            meta: self.meta,
+        }
+    }
+}
+
+/// Details of an HTTP response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Meta {
+    /// The requested URL, with the App ID redacted.
+    pub url: String,
+    pub status: u16,
+    /// Time from sending the request to reading the whole body.
+    pub latency: Duration,
+    /// The `Date` header.
+    pub date: Option<String>,
+    /// The `ETag` header.
+    pub etag: Option<String>,
+    /// The `Last-Modified` header.
+    pub last_modified: Option<String>,
+    /// The `Cache-Control` header.
+    pub cache_control: Option<String>,
+}
+
+impl Meta {
+    /// Collect the metadata of a response to `url`.
+    pub fn new(url: &str, status: u16, latency: Duration, headers: &Headers) -> Meta {
+        Meta {
+            url: redact(url),
+            status: status,
+            latency: latency,
+            date: header(headers, "Date"),
+            etag: header(headers, "ETag"),
+            last_modified: header(headers, "Last-Modified"),
+            cache_control: header(headers, "Cache-Control"),
+        }
+    }
+}
+
+/// Replace the value of the `app_id` parameter of `url`, so it can be logged.
+pub fn redact(url: &str) -> String {
+    let start = match url.find("app_id=") {
+        Some(index) => index + "app_id=".len(),
+        None => return url.to_owned(),
+    };
+    let end = url[start..].find('&').map_or(url.len(), |index| start + index);
+    format!("{}REDACTED{}", &url[..start], &url[end..])
+}
+
+fn header(headers: &Headers, name: &str) -> Option<String> {
+    headers.get_raw(name)
+        .and_then(|values| values.first())
+        .map(|value| String::from_utf8_lossy(value).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use hyper::header::Headers;
+
+    use super::*;
+
+    #[test]
+    fn redacts_app_id() {
+        assert_eq!(redact("https://openexchangerates.org/api/latest.json?app_id=1234&base=EUR"),
+                   "https://openexchangerates.org/api/latest.json?app_id=REDACTED&base=EUR");
+        assert_eq!(redact("https://openexchangerates.org/api/usage.json?app_id=1234"),
+                   "https://openexchangerates.org/api/usage.json?app_id=REDACTED");
+        assert_eq!(redact("http://localhost/api/latest.json"),
+                   "http://localhost/api/latest.json");
+    }
+
+    #[test]
+    fn reads_headers() {
+        let mut headers = Headers::new();
+        headers.set_raw("ETag", vec![b"\"abc\"".to_vec()]);
+        headers.set_raw("Cache-Control", vec![b"public".to_vec()]);
+
+        let meta = Meta::new("http://localhost/api/usage.json?app_id=1234",
+                             200,
+                             Duration::from_millis(5),
+                             &headers);
+        assert_eq!(meta.url, "http://localhost/api/usage.json?app_id=REDACTED");
+        assert_eq!(meta.etag, Some("\"abc\"".to_owned()));
+        assert_eq!(meta.cache_control, Some("public".to_owned()));
+        assert_eq!(meta.date, None);
+        assert_eq!(meta.last_modified, None);
+    }
+}
@@ -1,21 +1,40 @@
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ExchangeRate {
     pub disclaimer: String,
     pub license: String,
+    #[serde(deserialize_with = "::timestamp::deserialize_secs")]
     pub timestamp: i64,
     pub base: String,
-    pub rates: BTreeMap<String, f32>,
+    pub rates: BTreeMap<String, f64>,
+}
+
+/// Exchange rates returned with `show_bid_ask`, quoting each currency as bid, ask and mid.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BidAskRate {
+    pub disclaimer: String,
+    pub license: String,
+    #[serde(deserialize_with = "::timestamp::deserialize_secs")]
+    pub timestamp: i64,
+    pub base: String,
+    pub rates: BTreeMap<String, Quote>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    pub bid: f64,
+    pub ask: f64,
+    pub mid: f64,
 }
 
 pub type Currencies = BTreeMap<String, String>;
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Usage {
     pub status: u32,
     pub data: UsageData,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct UsageData {
     pub app_id: String,
     pub status: String,
@@ -23,7 +42,7 @@ pub struct UsageData {
     pub usage: UsageDataUsage,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct UsageDataPlan {
     pub name: String,
     pub quota: String,
@@ -31,17 +50,21 @@ pub struct UsageDataPlan {
     pub features: Features,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+/// Features of a plan. `Serialize` and `Deserialize` are implemented in the `features`
+/// module.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Features {
     pub base: bool,
     pub symbols: bool,
     pub experimental: bool,
-    #[serde(rename = "time-series")]
+    /// The `time-series` flag.
     pub time_series: bool,
     pub convert: bool,
+    /// Flags the crate does not know about yet, such as `bid-ask`.
+    pub extra: BTreeMap<String, bool>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct UsageDataUsage {
     pub requests: i64,
     pub requests_quota: i64,
@@ -49,4 +72,30 @@ pub struct UsageDataUsage {
     pub days_elapsed: i64,
     pub days_remaining: i64,
     pub daily_average: i64,
-}
\ No newline at end of file
+}
+
+/// Settings used to build a `Client`, usually loaded with `Config::from_file` or
+/// `Config::from_env`.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub app_id: String,
+    pub base_url: Option<String>,
+    /// Read and write timeout in seconds.
+    pub timeout: Option<u64>,
+    pub base: Option<String>,
+    /// Maximum age in seconds of the latest exchange rates, see `Client::max_age`.
+    pub max_age: Option<u64>,
+    /// Not used by `Client::from_config`; pass it to `provider::CachingProvider::from_config`.
+    pub cache: Option<CacheConfig>,
+}
+
+/// Settings for caching responses.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// How long a response stays fresh, in seconds.
+    pub ttl: u64,
+    /// Maximum number of responses kept.
+    pub capacity: Option<usize>,
+}
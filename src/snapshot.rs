@@ -0,0 +1,195 @@
+//! Snapshot module
+//!
+//! Embeds exchange rates into the binary at build time, for environments without network
+//! access. Point the build at JSON files in the format of the API, e.g. saved responses of
+//! `latest.json` and `currencies.json`, by absolute path since build scripts run in the
+//! directory of the crate:
+//!
+//! ```sh
+//! OXR_SNAPSHOT_RATES=$PWD/latest.json OXR_SNAPSHOT_CURRENCIES=$PWD/currencies.json cargo build
+//! ```
+//!
+//! Both variables are optional; without `OXR_SNAPSHOT_RATES` no snapshot is embedded. The build
+//! fails if a file is not valid JSON, or if the rates lack a field of `ExchangeRate` or have one
+//! of the wrong type. The `SnapshotProvider` then serves the embedded rates without any request:
+//!
+//! ```no_run
+//! use openexchangerates::provider::RateProvider;
+//! use openexchangerates::snapshot::SnapshotProvider;
+//!
+//! let provider = SnapshotProvider::embedded().unwrap();
+//! println!("offline rates of {}", provider.date());
+//! println!("{:?}", provider.latest().unwrap().convert(100.0, "USD", "MYR"));
+//! ```
+use std::fmt;
+
+use chrono::{DateTime, Duration, NaiveDate, UTC};
+use serde_json;
+
+use {Currencies, ExchangeRate};
+use error::Error;
+use provider::{RateProvider, StaticProvider};
+
+/// Contents of the `OXR_SNAPSHOT_RATES` file, empty if it was not set.
+pub const RATES: &'static str = include_str!(concat!(env!("OUT_DIR"), "/snapshot_rates.json"));
+
+/// Contents of the `OXR_SNAPSHOT_CURRENCIES` file, empty if it was not set.
+pub const CURRENCIES: &'static str = include_str!(concat!(env!("OUT_DIR"),
+                                                          "/snapshot_currencies.json"));
+
+/// Whether exchange rates were embedded at build time.
+pub fn is_embedded() -> bool {
+    !RATES.trim().is_empty()
+}
+
+/// Serves a single snapshot of exchange rates, and the currencies along with it, through a
+/// `StaticProvider`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnapshotProvider {
+    rate: ExchangeRate,
+    inner: StaticProvider,
+}
+
+impl SnapshotProvider {
+    /// Serve the snapshot embedded at build time.
+    ///
+    /// Fails with `Error::Unavailable` if none was embedded.
+    pub fn embedded() -> Result<SnapshotProvider, Error> {
+        if !is_embedded() {
+            return Err(Error::Unavailable("no exchange rates embedded at build time".to_owned()));
+        }
+        SnapshotProvider::from_json(RATES, CURRENCIES)
+    }
+
+    /// Serve the snapshot in `rates`, and the currencies in `currencies` unless it is empty.
+    pub fn from_json(rates: &str, currencies: &str) -> Result<SnapshotProvider, Error> {
+        let rate = try!(serde_json::from_str(rates));
+        let currencies = if currencies.trim().is_empty() {
+            Currencies::new()
+        } else {
+            try!(serde_json::from_str(currencies))
+        };
+        Ok(SnapshotProvider::new(rate, currencies))
+    }
+
+    /// Serve `rate` and `currencies`.
+    pub fn new(rate: ExchangeRate, currencies: Currencies) -> SnapshotProvider {
+        SnapshotProvider {
+            inner: StaticProvider::new(vec![rate.clone()]).with_currencies(currencies),
+            rate: rate,
+        }
+    }
+
+    /// The time at which the snapshot was published.
+    pub fn datetime(&self) -> DateTime<UTC> {
+        self.rate.datetime()
+    }
+
+    /// The business date of the snapshot, the only one `historical` serves.
+    pub fn date(&self) -> NaiveDate {
+        self.rate.date()
+    }
+
+    /// How old the snapshot is now.
+    pub fn age(&self) -> Duration {
+        self.rate.age()
+    }
+}
+
+impl RateProvider for SnapshotProvider {
+    fn latest(&self) -> Result<ExchangeRate, Error> {
+        self.inner.latest()
+    }
+
+    /// The snapshot if it is of `date`, or else `Error::Gap`.
+    fn historical(&self, date: NaiveDate) -> Result<ExchangeRate, Error> {
+        self.inner.historical(date)
+    }
+
+    fn currencies(&self) -> Result<Currencies, Error> {
+        self.inner.currencies()
+    }
+}
+
+impl fmt::Display for SnapshotProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "snapshot of {} ({} days old)",
+               self.datetime().format("%Y-%m-%d %H:%M UTC"),
+               self.age().num_days())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Read;
+
+    use chrono::NaiveDate;
+    use serde_json;
+
+    use ExchangeRate;
+    use error::Error;
+    use provider::RateProvider;
+    use super::*;
+
+    const RATES: &'static str = r#"{
+        "disclaimer": "Exchange rates provided for informational purposes only.",
+        "license": "Data sourced from various providers.",
+        "timestamp": 1459854002,
+        "base": "USD",
+        "rates": {"MYR": 3.9, "SGD": 1.35}
+    }"#;
+
+    #[test]
+    fn serves_the_snapshot() {
+        let provider = SnapshotProvider::from_json(RATES, r#"{"MYR": "Malaysian Ringgit"}"#)
+            .unwrap();
+        assert_eq!(provider.date(), NaiveDate::from_ymd(2016, 4, 5));
+        assert_eq!(provider.latest().unwrap().rates["MYR"], 3.9);
+        assert_eq!(provider.historical(provider.date()).unwrap().timestamp, 1459854002);
+        match provider.historical(NaiveDate::from_ymd(2016, 4, 4)) {
+            Err(Error::Gap(date)) => assert_eq!(date, NaiveDate::from_ymd(2016, 4, 4)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(provider.currencies().unwrap()["MYR"], "Malaysian Ringgit");
+        assert!(provider.to_string().starts_with("snapshot of 2016-04-05 11:00 UTC ("));
+    }
+
+    #[test]
+    fn currencies_are_optional() {
+        let provider = SnapshotProvider::from_json(RATES, "").unwrap();
+        assert!(provider.currencies().unwrap().is_empty());
+        assert!(SnapshotProvider::from_json("", "").is_err());
+    }
+
+    /// The contents of the file named by `path`, as given to the build script.
+    fn read(path: Option<&str>) -> String {
+        let mut contents = String::new();
+        if let Some(path) = path {
+            File::open(path).unwrap().read_to_string(&mut contents).unwrap();
+        }
+        contents
+    }
+
+    #[test]
+    fn embeds_the_files_given_at_build_time() {
+        assert_eq!(super::RATES, read(option_env!("OXR_SNAPSHOT_RATES")));
+        assert_eq!(super::CURRENCIES, read(option_env!("OXR_SNAPSHOT_CURRENCIES")));
+
+        match option_env!("OXR_SNAPSHOT_RATES") {
+            Some(path) => {
+                let rate: ExchangeRate = serde_json::from_str(&read(Some(path))).unwrap();
+                let provider = SnapshotProvider::embedded().unwrap();
+                assert_eq!(provider.latest().unwrap(), rate);
+                assert_eq!(provider.date(), rate.date());
+            }
+            None => {
+                match SnapshotProvider::embedded() {
+                    Err(Error::Unavailable(_)) => {}
+                    other => panic!("unexpected result: {:?}", other),
+                }
+            }
+        }
+    }
+}
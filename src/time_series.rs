@@ -0,0 +1,369 @@
+//! Time series module
+//!
+//! `Client::time_series` returns a `TimeSeries`, which reads the `time-series.json` response
+//! one day at a time instead of decoding the whole body at once:
+//!
+//! ```no_run
+//! # extern crate chrono;
+//! # extern crate openexchangerates;
+//! # fn main() {
+//! use chrono::NaiveDate;
+//! use openexchangerates::Client;
+//!
+//! let client = Client::new("YOUR_APP_ID");
+//! let (start, end) = (NaiveDate::from_ymd(2016, 1, 1), NaiveDate::from_ymd(2016, 12, 31));
+//! let series = client.time_series(start, end).unwrap();
+//! for rate in series {
+//!     let rate = rate.unwrap();
+//!     println!("{} {:?}", rate.date(), rate.rates.get("MYR"));
+//! }
+//! # }
+//! ```
+use std::collections::BTreeMap;
+use std::io::{self, BufReader, Read};
+
+use chrono::NaiveDate;
+use serde_json;
+use serde_json::Value;
+
+use ExchangeRate;
+use error::Error;
+
+/// Iterates over the days of a `time-series.json` response as they are read.
+///
+/// Each day becomes an `ExchangeRate` timestamped at midnight UTC. The fields preceding
+/// `rates` in the response, such as `base`, are read first; the API sends them in that order.
+pub struct TimeSeries<R> {
+    bytes: io::Bytes<BufReader<R>>,
+    peeked: Option<u8>,
+    disclaimer: String,
+    license: String,
+    base: String,
+    done: bool,
+    on_finish: Option<Box<FnMut(Option<&Error>) + Send>>,
+}
+
+impl<R> TimeSeries<R> {
+    /// Call `callback` once the series ends, with the error that ended it if any. Dropping the
+    /// series before its end counts as ending it without error.
+    pub fn on_finish<F>(mut self, callback: F) -> TimeSeries<R>
+        where F: FnMut(Option<&Error>) + Send + 'static
+    {
+        self.on_finish = Some(Box::new(callback));
+        self
+    }
+
+    fn finish(&mut self, error: Option<&Error>) {
+        self.done = true;
+        if let Some(mut callback) = self.on_finish.take() {
+            callback(error);
+        }
+    }
+}
+
+impl<R> Drop for TimeSeries<R> {
+    fn drop(&mut self) {
+        self.finish(None);
+    }
+}
+
+impl<R> TimeSeries<R>
+    where R: Read
+{
+    /// Start reading a response from `reader`, up to the first day.
+    ///
+    /// `base` is used if the response does not name its base currency before the rates.
+    pub fn new(reader: R, base: &str) -> Result<TimeSeries<R>, Error> {
+        let mut series = TimeSeries {
+            bytes: BufReader::new(reader).bytes(),
+            peeked: None,
+            disclaimer: String::new(),
+            license: String::new(),
+            base: base.to_owned(),
+            done: false,
+            on_finish: None,
+        };
+        try!(series.read_header());
+        Ok(series)
+    }
+
+    pub fn disclaimer(&self) -> &str {
+        &self.disclaimer
+    }
+
+    pub fn license(&self) -> &str {
+        &self.license
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Read the top-level fields until the opening brace of `rates`.
+    fn read_header(&mut self) -> Result<(), Error> {
+        try!(self.expect(b'{'));
+        let mut fields = BTreeMap::new();
+        loop {
+            match try!(self.next_token()) {
+                b'"' => {}
+                b'}' => return Err(header_error(&fields)),
+                b => return Err(unexpected(b)),
+            }
+            let key = try!(self.read_key());
+            if key == "rates" {
+                return self.expect(b'{');
+            }
+
+            let value: Value = try!(serde_json::from_slice(&try!(self.read_value())));
+            match (key.as_ref(), value.as_str()) {
+                ("disclaimer", Some(s)) => self.disclaimer = s.to_owned(),
+                ("license", Some(s)) => self.license = s.to_owned(),
+                ("base", Some(s)) => self.base = s.to_owned(),
+                _ => {}
+            }
+            fields.insert(key, value);
+
+            match try!(self.next_token()) {
+                b',' => {}
+                b'}' => return Err(header_error(&fields)),
+                b => return Err(unexpected(b)),
+            }
+        }
+    }
+
+    fn read_day(&mut self) -> Result<Option<ExchangeRate>, Error> {
+        let mut token = try!(self.next_token());
+        if token == b',' {
+            token = try!(self.next_token());
+        }
+        match token {
+            b'}' => return Ok(None),
+            b'"' => {}
+            b => return Err(unexpected(b)),
+        }
+
+        let key = try!(self.read_key());
+        let date = match NaiveDate::parse_from_str(&key, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => return Err(Error::Decode(format!("invalid date `{}`", key))),
+        };
+        let rates: BTreeMap<String, f64> = try!(serde_json::from_slice(&try!(self.read_value())));
+        Ok(Some(ExchangeRate {
+            disclaimer: self.disclaimer.clone(),
+            license: self.license.clone(),
+            timestamp: date.and_hms(0, 0, 0).timestamp(),
+            base: self.base.clone(),
+            rates: rates,
+        }))
+    }
+
+    /// Read an object key, after its opening quote, and the following colon.
+    fn read_key(&mut self) -> Result<String, Error> {
+        let mut raw = vec![b'"'];
+        try!(self.read_string(&mut raw));
+        try!(self.expect(b':'));
+        Ok(try!(serde_json::from_slice(&raw)))
+    }
+
+    /// Read the raw bytes of the next JSON value.
+    fn read_value(&mut self) -> Result<Vec<u8>, Error> {
+        let mut raw = Vec::new();
+        let first = try!(self.next_token());
+        raw.push(first);
+        match first {
+            b'"' => try!(self.read_string(&mut raw)),
+            b'{' | b'[' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    let b = try!(self.next_byte());
+                    raw.push(b);
+                    match b {
+                        b'"' => try!(self.read_string(&mut raw)),
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {
+                loop {
+                    let b = try!(self.next_byte());
+                    if b == b',' || b == b'}' || b == b']' || is_whitespace(b) {
+                        self.peeked = Some(b);
+                        break;
+                    }
+                    raw.push(b);
+                }
+            }
+        }
+        Ok(raw)
+    }
+
+    /// Read the rest of a string, after its opening quote, into `raw`.
+    fn read_string(&mut self, raw: &mut Vec<u8>) -> Result<(), Error> {
+        loop {
+            let b = try!(self.next_byte());
+            raw.push(b);
+            match b {
+                b'\\' => raw.push(try!(self.next_byte())),
+                b'"' => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), Error> {
+        match try!(self.next_token()) {
+            b if b == expected => Ok(()),
+            b => Err(unexpected(b)),
+        }
+    }
+
+    /// The next byte that is not whitespace.
+    fn next_token(&mut self) -> Result<u8, Error> {
+        loop {
+            let b = try!(self.next_byte());
+            if !is_whitespace(b) {
+                return Ok(b);
+            }
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<u8, Error> {
+        if let Some(b) = self.peeked.take() {
+            return Ok(b);
+        }
+        match self.bytes.next() {
+            Some(b) => Ok(try!(b)),
+            None => Err(Error::Decode("unexpected end of response".to_owned())),
+        }
+    }
+}
+
+impl<R> Iterator for TimeSeries<R>
+    where R: Read
+{
+    type Item = Result<ExchangeRate, Error>;
+
+    fn next(&mut self) -> Option<Result<ExchangeRate, Error>> {
+        if self.done {
+            return None;
+        }
+        match self.read_day() {
+            Ok(Some(rate)) => Some(Ok(rate)),
+            Ok(None) => {
+                self.finish(None);
+                None
+            }
+            Err(err) => {
+                self.finish(Some(&err));
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    b == b' ' || b == b'\n' || b == b'\r' || b == b'\t'
+}
+
+fn unexpected(b: u8) -> Error {
+    Error::Decode(format!("unexpected `{}` in time series", b as char))
+}
+
+/// The error for a response without `rates`, using the API error message if there is one.
+fn header_error(fields: &BTreeMap<String, Value>) -> Error {
+    match fields.get("description").and_then(|description| description.as_str()) {
+        Some(description) => Error::Decode(description.to_owned()),
+        None => Error::Decode("time series has no rates".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::NaiveDate;
+
+    use error::Error;
+    use super::*;
+
+    const SERIES: &'static str = r#"{
+        "disclaimer": "Exchange rates \"as is\"",
+        "license": "Data sourced {from} various providers",
+        "start_date": "2016-04-04",
+        "end_date": "2016-04-05",
+        "base": "EUR",
+        "rates": {
+            "2016-04-04": { "MYR": 4.4515, "USD": 1.1379 },
+            "2016-04-05": { "MYR": 4.4591, "USD": 1.1386 }
+        }
+    }"#;
+
+    #[test]
+    fn reads_days() {
+        let series = TimeSeries::new(SERIES.as_bytes(), "USD").unwrap();
+        assert_eq!(series.base(), "EUR");
+        assert_eq!(series.disclaimer(), "Exchange rates \"as is\"");
+        assert_eq!(series.license(), "Data sourced {from} various providers");
+
+        let rates: Vec<_> = series.map(|rate| rate.unwrap()).collect();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].date(), NaiveDate::from_ymd(2016, 4, 4));
+        assert_eq!(rates[0].base, "EUR");
+        assert_eq!(rates[1].rates.get("MYR"), Some(&4.4591));
+    }
+
+    #[test]
+    fn calls_on_finish_once() {
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let recorded = finished.clone();
+        let series = TimeSeries::new(SERIES.as_bytes(), "USD")
+            .unwrap()
+            .on_finish(move |error| recorded.lock().unwrap().push(error.map(|err| err.kind())));
+        assert_eq!(series.count(), 2);
+        assert_eq!(*finished.lock().unwrap(), vec![None]);
+
+        let truncated = &SERIES[..SERIES.rfind("\"2016-04-05\"").unwrap()];
+        let recorded = finished.clone();
+        let mut series = TimeSeries::new(truncated.as_bytes(), "USD")
+            .unwrap()
+            .on_finish(move |error| recorded.lock().unwrap().push(error.map(|err| err.kind())));
+        assert!(series.next().unwrap().is_ok());
+        assert!(series.next().unwrap().is_err());
+        assert!(series.next().is_none());
+        drop(series);
+        assert_eq!(*finished.lock().unwrap(), vec![None, Some("decode")]);
+
+        let recorded = finished.clone();
+        TimeSeries::new(SERIES.as_bytes(), "USD")
+            .unwrap()
+            .on_finish(move |error| recorded.lock().unwrap().push(error.map(|err| err.kind())));
+        assert_eq!(*finished.lock().unwrap(), vec![None, Some("decode"), None]);
+    }
+
+    #[test]
+    fn empty_rates() {
+        let series = TimeSeries::new(r#"{"base": "USD", "rates": {}}"#.as_bytes(), "USD");
+        assert_eq!(series.unwrap().count(), 0);
+    }
+
+    #[test]
+    fn api_error() {
+        let body = r#"{"error": true, "status": 403, "message": "not_allowed",
+                       "description": "Time-series requests require an Enterprise plan."}"#;
+        match TimeSeries::new(body.as_bytes(), "USD") {
+            Err(Error::Decode(msg)) => assert!(msg.starts_with("Time-series requests")),
+            _ => panic!("expected a decode error"),
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let body = r#"{"base": "USD", "rates": {"2016-04-04": {"MYR": 3.9}, "2016-04-05": {"#;
+        let mut series = TimeSeries::new(body.as_bytes(), "USD").unwrap();
+        assert!(series.next().unwrap().is_ok());
+        assert!(series.next().unwrap().is_err());
+        assert!(series.next().is_none());
+    }
+}
@@ -0,0 +1,196 @@
+//! Timestamp module
+//!
+//! OpenExchangeRates reports the time of a snapshot as UNIX seconds. Besides the accessors on
+//! `ExchangeRate`, this module can be used with `serialize_with` and `deserialize_with` to keep
+//! such timestamps as `DateTime<UTC>` in your own types:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Snapshot {
+//!     #[serde(serialize_with = "openexchangerates::timestamp::serialize",
+//!             deserialize_with = "openexchangerates::timestamp::deserialize")]
+//!     timestamp: DateTime<UTC>,
+//! }
+//! ```
+//!
+//! The decoders of this crate reject timestamps outside of the range of `chrono` with `check`,
+//! so that `ExchangeRate::datetime` and the helpers built on it do not panic.
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, UTC};
+use serde::{Deserialize, Deserializer, Serializer};
+use serde::de::Error;
+
+use {BidAskRate, ExchangeRate};
+use error;
+
+/// Serialize `datetime` as UNIX seconds.
+pub fn serialize<S>(datetime: &DateTime<UTC>, serializer: &mut S) -> Result<(), S::Error>
+    where S: Serializer
+{
+    serializer.serialize_i64(datetime.timestamp())
+}
+
+/// Deserialize UNIX seconds into a `DateTime<UTC>`.
+pub fn deserialize<D>(deserializer: &mut D) -> Result<DateTime<UTC>, D::Error>
+    where D: Deserializer
+{
+    let secs = try!(i64::deserialize(deserializer));
+    from_secs(secs).ok_or_else(|| D::Error::invalid_value("timestamp out of range"))
+}
+
+/// Deserialize UNIX seconds, rejecting those out of the range of `DateTime<UTC>`.
+pub fn deserialize_secs<D>(deserializer: &mut D) -> Result<i64, D::Error>
+    where D: Deserializer
+{
+    let secs = try!(i64::deserialize(deserializer));
+    match from_secs(secs) {
+        Some(_) => Ok(secs),
+        None => Err(D::Error::invalid_value("timestamp out of range")),
+    }
+}
+
+/// Fail with `Error::Decode` if `secs` is out of the range of `DateTime<UTC>`.
+pub fn check(secs: i64) -> Result<i64, error::Error> {
+    match from_secs(secs) {
+        Some(_) => Ok(secs),
+        None => Err(error::Error::Decode(format!("timestamp {} out of range", secs))),
+    }
+}
+
+fn from_secs(secs: i64) -> Option<DateTime<UTC>> {
+    UTC.timestamp_opt(secs, 0).single()
+}
+
+impl ExchangeRate {
+    /// The time at which the rates were published.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `timestamp` is outside of the range supported by `chrono`. The decoders of this
+    /// crate reject such timestamps, so this only happens with hand-built values.
+    pub fn datetime(&self) -> DateTime<UTC> {
+        from_secs(self.timestamp).expect("timestamp out of range")
+    }
+
+    /// The business date the rates belong to, in UTC.
+    ///
+    /// This is the date to pass to `Client::historical` to get the same snapshot again.
+    pub fn date(&self) -> NaiveDate {
+        self.datetime().naive_utc().date()
+    }
+
+    /// How old the rates are now.
+    pub fn age(&self) -> Duration {
+        self.age_at(UTC::now())
+    }
+
+    /// How old the rates are at `now`.
+    pub fn age_at(&self, now: DateTime<UTC>) -> Duration {
+        now - self.datetime()
+    }
+
+    /// Fail with `Error::Stale` if the rates are older than `max_age`.
+    pub fn check_age(&self, max_age: Duration) -> Result<(), error::Error> {
+        check_age(self.datetime(), max_age)
+    }
+}
+
+impl BidAskRate {
+    /// Fail with `Error::Stale` if the quotes are older than `max_age`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `timestamp` is out of range, like `ExchangeRate::datetime`.
+    pub fn check_age(&self, max_age: Duration) -> Result<(), error::Error> {
+        check_age(from_secs(self.timestamp).expect("timestamp out of range"), max_age)
+    }
+}
+
+fn check_age(datetime: DateTime<UTC>, max_age: Duration) -> Result<(), error::Error> {
+    let age = UTC::now() - datetime;
+    if age > max_age {
+        Err(error::Error::Stale { age: age })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::*;
+    use serde_json;
+
+    use {BidAskRate, ExchangeRate};
+    use error::Error;
+
+    fn rate(timestamp: i64) -> ExchangeRate {
+        ExchangeRate::fixture(timestamp, "USD", &[])
+    }
+
+    #[test]
+    fn datetime_and_date() {
+        let rate = rate(1361055600);
+        assert_eq!(rate.datetime(), UTC.ymd(2013, 2, 16).and_hms(23, 0, 0));
+        assert_eq!(rate.date(), NaiveDate::from_ymd(2013, 2, 16));
+    }
+
+    #[test]
+    fn age_at() {
+        let rate = rate(1459854002);
+        let now = UTC.timestamp(1459854002 + 5400, 0);
+        assert_eq!(rate.age_at(now), Duration::minutes(90));
+    }
+
+    #[test]
+    fn check_age() {
+        let fresh = rate(UTC::now().timestamp() - 60);
+        assert!(fresh.check_age(Duration::hours(1)).is_ok());
+
+        let stale = rate(UTC::now().timestamp() - 7200);
+        match stale.check_age(Duration::hours(1)) {
+            Err(Error::Stale { age }) => assert!(age >= Duration::hours(2)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn check_age_of_quotes() {
+        let quotes = BidAskRate {
+            disclaimer: String::new(),
+            license: String::new(),
+            timestamp: UTC::now().timestamp() - 7200,
+            base: "USD".to_owned(),
+            rates: BTreeMap::new(),
+        };
+        assert!(quotes.check_age(Duration::hours(3)).is_ok());
+        match quotes.check_age(Duration::hours(1)) {
+            Err(Error::Stale { age }) => assert!(age >= Duration::hours(2)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn serde_adapter() {
+        let datetime = UTC.timestamp(1459854002, 0);
+
+        let mut serializer = serde_json::Serializer::new(Vec::new());
+        super::serialize(&datetime, &mut serializer).unwrap();
+        assert_eq!(serializer.into_inner(), b"1459854002");
+
+        let mut deserializer = serde_json::Deserializer::new("1459854002".bytes().map(Ok));
+        assert_eq!(super::deserialize(&mut deserializer).unwrap(), datetime);
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        assert_eq!(super::check(1459854002).unwrap(), 1459854002);
+        assert!(super::check(i64::max_value()).is_err());
+
+        let huge = i64::max_value().to_string();
+        let mut deserializer = serde_json::Deserializer::new(huge.bytes().map(Ok));
+        assert!(super::deserialize_secs(&mut deserializer).is_err());
+        let mut deserializer = serde_json::Deserializer::new("-86400".bytes().map(Ok));
+        assert_eq!(super::deserialize_secs(&mut deserializer).unwrap(), -86400);
+    }
+}
@@ -0,0 +1,337 @@
+//! Watcher module
+//!
+//! A `RateWatcher` polls `Client::latest` and reports the currencies whose rate moved between
+//! two consecutive snapshots:
+//!
+//! ```no_run
+//! use openexchangerates::Client;
+//! use openexchangerates::watcher::RateWatcher;
+//!
+//! let client = Client::new("YOUR_APP_ID");
+//! let mut watcher = RateWatcher::new(client)
+//!     .aligned_to_plan()
+//!     .unwrap()
+//!     .threshold("MYR", 0.5)
+//!     .on_change(|change| println!("{:?}", change));
+//! let changes = watcher.subscribe();
+//! watcher.spawn();
+//!
+//! for change in changes {
+//!     println!("{} moved {:.2}%", change.currency, change.percent);
+//! }
+//! ```
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use {Client, ExchangeRate};
+use error::Error;
+
+/// How long to wait after an update is due before polling, so the new snapshot is published.
+const ALIGNMENT_DELAY: u64 = 30;
+
+/// A currency whose rate changed between two snapshots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateChange {
+    pub currency: String,
+    pub old: f64,
+    pub new: f64,
+    /// Relative change from `old` to `new`, in percent.
+    pub percent: f64,
+    /// Timestamp of the snapshot holding `new`.
+    pub timestamp: i64,
+}
+
+/// Polls the latest exchange rates and delivers `RateChange`s.
+pub struct RateWatcher<'a> {
+    client: Client<'a>,
+    interval: Duration,
+    aligned: bool,
+    default_threshold: f64,
+    thresholds: BTreeMap<String, f64>,
+    callbacks: Vec<Box<Fn(&RateChange) + Send>>,
+    error_callbacks: Vec<Box<Fn(&Error) + Send>>,
+    senders: Vec<Sender<RateChange>>,
+    last: Option<ExchangeRate>,
+}
+
+impl<'a> RateWatcher<'a> {
+    /// Create a watcher polling every hour, reporting every change.
+    pub fn new(client: Client<'a>) -> RateWatcher<'a> {
+        RateWatcher {
+            client: client,
+            interval: Duration::from_secs(3600),
+            aligned: false,
+            default_threshold: 0.0,
+            thresholds: BTreeMap::new(),
+            callbacks: Vec::new(),
+            error_callbacks: Vec::new(),
+            senders: Vec::new(),
+            last: None,
+        }
+    }
+
+    /// Poll every `interval`.
+    pub fn interval(mut self, interval: Duration) -> RateWatcher<'a> {
+        self.interval = interval;
+        self.aligned = false;
+        self
+    }
+
+    /// Poll as often as the plan of the App ID updates the rates, shortly after each update.
+    ///
+    /// This calls `Client::usage` to find the update frequency of the plan.
+    pub fn aligned_to_plan(mut self) -> Result<RateWatcher<'a>, Error> {
+        let usage = try!(self.client.usage());
+        let frequency = &usage.data.plan.update_frequency;
+        match update_interval(frequency) {
+            Some(interval) => {
+                self.interval = interval;
+                self.aligned = true;
+                Ok(self)
+            }
+            None => Err(Error::Config(format!("unknown update frequency `{}`", frequency))),
+        }
+    }
+
+    /// Only report changes of at least `percent`, in either direction, for currencies without
+    /// their own threshold.
+    pub fn default_threshold(mut self, percent: f64) -> RateWatcher<'a> {
+        self.default_threshold = percent;
+        self
+    }
+
+    /// Only report changes of `currency` of at least `percent`, in either direction.
+    pub fn threshold<S>(mut self, currency: S, percent: f64) -> RateWatcher<'a>
+        where S: Into<String>
+    {
+        self.thresholds.insert(currency.into(), percent);
+        self
+    }
+
+    /// Call `callback` with every change.
+    pub fn on_change<F>(mut self, callback: F) -> RateWatcher<'a>
+        where F: Fn(&RateChange) + Send + 'static
+    {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Call `callback` when polling fails. The watcher keeps polling afterwards.
+    pub fn on_error<F>(mut self, callback: F) -> RateWatcher<'a>
+        where F: Fn(&Error) + Send + 'static
+    {
+        self.error_callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Receive every change through a channel.
+    pub fn subscribe(&mut self) -> Receiver<RateChange> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Fetch the latest exchange rates once and deliver the changes since the previous poll.
+    ///
+    /// The first poll only records the snapshot. Fails if the snapshots cannot be compared, e.g.
+    /// when the base currency changed to one without a rate in the previous snapshot; the new
+    /// snapshot is recorded anyway, so the next poll compares against it.
+    pub fn poll(&mut self) -> Result<Vec<RateChange>, Error> {
+        let rate = try!(self.client.latest());
+        self.process(rate)
+    }
+
+    /// Poll forever, sleeping for the interval in between.
+    pub fn run(mut self) {
+        loop {
+            if let Err(err) = self.poll() {
+                for callback in &self.error_callbacks {
+                    callback(&err);
+                }
+            }
+            thread::sleep(self.wait());
+        }
+    }
+
+    fn process(&mut self, rate: ExchangeRate) -> Result<Vec<RateChange>, Error> {
+        let changes = match self.last {
+            Some(ref last) if last.timestamp != rate.timestamp => {
+                changes(last, &rate, self.default_threshold, &self.thresholds)
+            }
+            _ => Ok(Vec::new()),
+        };
+        if self.last.as_ref().map_or(true, |last| last.timestamp != rate.timestamp) {
+            self.last = Some(rate);
+        }
+        let changes = try!(changes);
+
+        for change in &changes {
+            for callback in &self.callbacks {
+                callback(change);
+            }
+            self.senders.retain(|sender| sender.send(change.clone()).is_ok());
+        }
+        Ok(changes)
+    }
+
+    /// How long to sleep before the next poll.
+    fn wait(&self) -> Duration {
+        if !self.aligned {
+            return self.interval;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0));
+        aligned_wait(now.as_secs(), self.interval.as_secs())
+    }
+}
+
+impl RateWatcher<'static> {
+    /// Poll forever in a new thread.
+    pub fn spawn(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || self.run())
+    }
+}
+
+/// The interval between updates of a plan, given its `update_frequency` such as `hourly` or
+/// `30-minute`.
+pub fn update_interval(frequency: &str) -> Option<Duration> {
+    let minutes = match frequency {
+        "hourly" => return Some(Duration::from_secs(3600)),
+        "daily" => return Some(Duration::from_secs(86400)),
+        _ if frequency.ends_with("-minutes") => &frequency[..frequency.len() - 8],
+        _ if frequency.ends_with("-minute") => &frequency[..frequency.len() - 7],
+        _ => return None,
+    };
+    match minutes.parse::<u64>() {
+        Ok(minutes) if minutes > 0 => Some(Duration::from_secs(minutes * 60)),
+        _ => None,
+    }
+}
+
+/// Seconds to wait from `now` until `ALIGNMENT_DELAY` after the next multiple of `interval`.
+fn aligned_wait(now: u64, interval: u64) -> Duration {
+    if interval == 0 {
+        return Duration::from_secs(0);
+    }
+    let since_update = now % interval;
+    let wait = if since_update < ALIGNMENT_DELAY {
+        ALIGNMENT_DELAY - since_update
+    } else {
+        interval - since_update + ALIGNMENT_DELAY
+    };
+    Duration::from_secs(wait)
+}
+
+/// The changes from `old` to `new` that reach their threshold.
+fn changes(old: &ExchangeRate,
+           new: &ExchangeRate,
+           default_threshold: f64,
+           thresholds: &BTreeMap<String, f64>)
+           -> Result<Vec<RateChange>, Error> {
+    let diff = try!(old.diff(new));
+    let changes = diff.moved()
+        .into_iter()
+        .filter(|&(currency, change)| {
+            let threshold = thresholds.get(currency).cloned().unwrap_or(default_threshold);
+            change.percent().abs() >= threshold
+        })
+        .map(|(currency, change)| {
+            RateChange {
+                currency: currency.to_owned(),
+                old: change.old,
+                new: change.new,
+                percent: change.percent(),
+                timestamp: new.timestamp,
+            }
+        })
+        .collect();
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use {Client, ExchangeRate};
+    use error::Error;
+    use super::*;
+
+    fn rate(timestamp: i64, rates: &[(&str, f64)]) -> ExchangeRate {
+        ExchangeRate::fixture(timestamp, "USD", rates)
+    }
+
+    #[test]
+    fn update_intervals() {
+        assert_eq!(update_interval("hourly"), Some(Duration::from_secs(3600)));
+        assert_eq!(update_interval("daily"), Some(Duration::from_secs(86400)));
+        assert_eq!(update_interval("30-minute"), Some(Duration::from_secs(1800)));
+        assert_eq!(update_interval("5-minutes"), Some(Duration::from_secs(300)));
+        assert_eq!(update_interval("weekly"), None);
+        assert_eq!(update_interval("0-minute"), None);
+    }
+
+    #[test]
+    fn aligned_waits() {
+        assert_eq!(super::aligned_wait(7200, 3600), Duration::from_secs(30));
+        assert_eq!(super::aligned_wait(7210, 3600), Duration::from_secs(20));
+        assert_eq!(super::aligned_wait(7230, 3600), Duration::from_secs(3600));
+        assert_eq!(super::aligned_wait(9000, 3600), Duration::from_secs(1830));
+    }
+
+    #[test]
+    fn process_reports_changes_over_threshold() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let callback_seen = seen.clone();
+        let mut watcher = RateWatcher::new(Client::new("1234"))
+            .default_threshold(1.0)
+            .threshold("EUR", 0.0)
+            .on_change(move |change| callback_seen.lock().unwrap().push(change.currency.clone()));
+        let receiver = watcher.subscribe();
+
+        assert!(watcher.process(rate(1, &[("EUR", 0.8), ("MYR", 4.0), ("SGD", 1.35)]))
+            .unwrap()
+            .is_empty());
+        let changes = watcher.process(rate(2, &[("EUR", 0.801), ("MYR", 4.2), ("SGD", 1.351)]))
+            .unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].currency, "EUR");
+        assert_eq!(changes[1],
+                   RateChange {
+                       currency: "MYR".to_owned(),
+                       old: 4.0,
+                       new: 4.2,
+                       percent: (4.2 - 4.0) / 4.0 * 100.0,
+                       timestamp: 2,
+                   });
+        assert_eq!(*seen.lock().unwrap(), vec!["EUR".to_owned(), "MYR".to_owned()]);
+        assert_eq!(receiver.try_recv().unwrap().currency, "EUR");
+        assert_eq!(receiver.try_recv().unwrap().currency, "MYR");
+    }
+
+    #[test]
+    fn process_ignores_same_snapshot() {
+        let mut watcher = RateWatcher::new(Client::new("1234"));
+        watcher.process(rate(1, &[("MYR", 4.0)])).unwrap();
+        assert!(watcher.process(rate(1, &[("MYR", 4.2)])).unwrap().is_empty());
+        assert_eq!(watcher.process(rate(2, &[("MYR", 4.2)])).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn process_fails_when_snapshots_cannot_be_compared() {
+        let mut watcher = RateWatcher::new(Client::new("1234"));
+        watcher.process(rate(1, &[("MYR", 4.0)])).unwrap();
+
+        let mut eur = rate(2, &[("MYR", 4.4)]);
+        eur.base = "EUR".to_owned();
+        match watcher.process(eur) {
+            Err(Error::MissingRate(currency)) => assert_eq!(currency, "USD"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut eur = rate(3, &[("MYR", 4.5)]);
+        eur.base = "EUR".to_owned();
+        assert_eq!(watcher.process(eur).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,119 @@
+//! Decodes every recorded response in `tests/fixtures`, to catch changes of the API schema.
+//!
+//! Fixtures are named after their endpoint, optionally followed by a variant such as the plan:
+//! `usage_enterprise.json`. Record a new payload there when the API changes.
+extern crate openexchangerates;
+extern crate serde;
+extern crate serde_json;
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use openexchangerates::{BidAskRate, Currencies, ExchangeRate, Usage};
+use openexchangerates::catalog::{self, CurrencyKind};
+use openexchangerates::lenient;
+use openexchangerates::time_series::TimeSeries;
+use serde_json::Value;
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn read(path: &Path) -> String {
+    let mut contents = String::new();
+    File::open(path).unwrap().read_to_string(&mut contents).unwrap();
+    contents
+}
+
+fn decode<T>(path: &Path, contents: &str)
+    where T: serde::Deserialize + serde::Serialize
+{
+    let decoded: T = serde_json::from_str(contents)
+        .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    serde_json::to_string(&decoded).unwrap();
+}
+
+#[test]
+fn every_fixture_decodes() {
+    let paths = fixtures();
+    assert!(!paths.is_empty());
+
+    for path in &paths {
+        let name = path.file_stem().unwrap().to_str().unwrap().to_owned();
+        let contents = read(path);
+
+        if name.starts_with("latest_bid_ask") || name.starts_with("historical_bid_ask") {
+            decode::<BidAskRate>(path, &contents);
+        } else if name.starts_with("latest") || name.starts_with("historical") {
+            decode::<ExchangeRate>(path, &contents);
+            let value: Value = serde_json::from_str(&contents).unwrap();
+            assert!(lenient::exchange_rate(&value).unwrap().warnings.is_empty(),
+                    "{}",
+                    path.display());
+        } else if name.starts_with("currencies") {
+            decode::<Currencies>(path, &contents);
+        } else if name.starts_with("usage") {
+            decode::<Usage>(path, &contents);
+            let value: Value = serde_json::from_str(&contents).unwrap();
+            assert!(lenient::usage(&value).unwrap().warnings.is_empty(),
+                    "{}",
+                    path.display());
+        } else if name.starts_with("time_series") {
+            let series = TimeSeries::new(contents.as_bytes(), "USD").unwrap();
+            for rate in series {
+                rate.unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+            }
+        } else {
+            panic!("{}: no decoder for this fixture", path.display());
+        }
+    }
+}
+
+#[test]
+fn every_plan_tier_is_recorded() {
+    let names: Vec<String> = fixtures()
+        .iter()
+        .map(|path| path.file_stem().unwrap().to_str().unwrap().to_owned())
+        .collect();
+    for plan in &["free", "developer", "enterprise", "unlimited"] {
+        let name = format!("usage_{}", plan);
+        assert!(names.contains(&name), "missing fixture {}.json", name);
+    }
+}
+
+#[test]
+fn extra_plan_features_are_kept() {
+    let path = fixtures().into_iter().find(|path| path.ends_with("usage_unlimited.json")).unwrap();
+    let usage: Usage = serde_json::from_str(&read(&path)).unwrap();
+    let features = &usage.data.plan.features;
+    assert!(features.time_series);
+    assert_eq!(features.get("bid-ask"), Some(true));
+    assert_eq!(features.get("spot"), Some(false));
+}
+
+#[test]
+fn currencies_are_in_the_catalog() {
+    let path = fixtures()
+        .into_iter()
+        .find(|path| path.ends_with("currencies_alternative_inactive.json"))
+        .unwrap();
+    let currencies: Currencies = serde_json::from_str(&read(&path)).unwrap();
+    for code in currencies.keys() {
+        assert!(catalog::lookup(code).is_some(), "{} is not in the catalog", code);
+    }
+    let kind = |code: &str| catalog::lookup(code).unwrap().kind;
+    assert_eq!(kind("EUR"), CurrencyKind::Active);
+    assert_eq!(kind("MYR"), CurrencyKind::Active);
+    assert_eq!(kind("BTC"), CurrencyKind::Alternative);
+    assert_eq!(kind("CNH"), CurrencyKind::Alternative);
+    assert_eq!(kind("XAU"), CurrencyKind::Alternative);
+    assert_eq!(kind("VEF"), CurrencyKind::Obsolete);
+}